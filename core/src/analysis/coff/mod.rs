@@ -0,0 +1,72 @@
+use failure::Error;
+use goblin::pe::Coff;
+use log::debug;
+
+use super::{
+    super::{arch::RVA, workspace::Workspace},
+    Analyzer,
+};
+
+/// a COFF object file carries no entry point: every function-typed symbol
+/// defined in one of its sections is a plausible analysis root, since any
+/// of them may be called once this object is linked into a final image.
+/// the symbol's storage class is not checked, so static functions are
+/// seeded as well as external ones.
+///
+/// this is the COFF counterpart to `pe::EntryPointAnalyzer`/
+/// `elf::EntryPointAnalyzer`/`macho::EntryPointAnalyzer`, except it seeds
+/// many starts rather than one.
+pub struct CoffSymbolAnalyzer {}
+
+impl CoffSymbolAnalyzer {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> CoffSymbolAnalyzer {
+        CoffSymbolAnalyzer {}
+    }
+}
+
+impl Analyzer for CoffSymbolAnalyzer {
+    fn get_name(&self) -> String {
+        "COFF symbol analyzer".to_string()
+    }
+
+    fn analyze(&self, ws: &mut Workspace) -> Result<(), Error> {
+        let coff = match Coff::parse(&ws.buf) {
+            Ok(coff) => coff,
+            Err(e) => {
+                debug!("COFF symbol analyzer: failed to re-parse object: {}", e);
+                return Ok(());
+            }
+        };
+
+        for (_, _, sym) in coff.symbols.iter().filter_map(|r| r.ok()) {
+            // `section_number` is 1-based; symbols that aren't defined in a
+            // section of this object (externs, absolutes, debug symbols)
+            // are out of scope here.
+            if sym.section_number < 1 {
+                continue;
+            }
+            // the derived type, `typ >> 4`, is `IMAGE_SYM_DTYPE_FUNCTION` (2,
+            // so `typ` is 0x20 plus the base type) for a function; plenty of toolchains leave this at zero, so this is
+            // a best-effort filter, not a guarantee.
+            let is_function = (sym.typ >> 4) == 0x2;
+            if !is_function {
+                continue;
+            }
+
+            let section_index = (sym.section_number - 1) as usize;
+            let section = match ws.module.sections.get(section_index) {
+                Some(section) => section,
+                None => continue,
+            };
+
+            let rva = section.addr + RVA::from(i64::from(sym.value));
+            debug!("COFF: symbol-derived function candidate at {}", rva);
+            if let Err(e) = ws.make_function(rva) {
+                debug!("COFF: failed to make function at {}: {}", rva, e);
+            }
+        }
+
+        ws.analyze()
+    }
+}
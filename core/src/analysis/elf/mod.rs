@@ -0,0 +1,43 @@
+use failure::Error;
+use goblin::Object;
+use log::debug;
+
+use super::{
+    super::{arch::VA, workspace::Workspace},
+    Analyzer,
+};
+
+pub struct EntryPointAnalyzer {}
+
+impl EntryPointAnalyzer {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> EntryPointAnalyzer {
+        EntryPointAnalyzer {}
+    }
+}
+
+impl Analyzer for EntryPointAnalyzer {
+    fn get_name(&self) -> String {
+        "ELF entry point analyzer".to_string()
+    }
+
+    fn analyze(&self, ws: &mut Workspace) -> Result<(), Error> {
+        let elf = match Object::parse(&ws.buf) {
+            Ok(Object::Elf(elf)) => elf,
+            _ => panic!("can't analyze unexpected format"),
+        };
+
+        // unlike a PE's AddressOfEntryPoint, ELF's e_entry is an absolute
+        // virtual address, so it must be rebased to an RVA before use.
+        let entry = ws
+            .rva(VA::from(elf.entry))
+            .ok_or_else(|| failure::err_msg("entry point is not mapped"))?;
+        debug!("entry point: {}", entry);
+
+        ws.make_symbol(entry, "entry")?;
+        ws.make_function(entry)?;
+        ws.analyze()?;
+
+        Ok(())
+    }
+}
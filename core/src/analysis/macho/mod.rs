@@ -0,0 +1,61 @@
+use failure::Error;
+use goblin::{
+    mach::{constants::cputype, Mach},
+    Object,
+};
+use log::debug;
+
+use super::{
+    super::{
+        arch::{Arch, VA},
+        workspace::Workspace,
+    },
+    Analyzer,
+};
+
+pub struct EntryPointAnalyzer {}
+
+impl EntryPointAnalyzer {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> EntryPointAnalyzer {
+        EntryPointAnalyzer {}
+    }
+}
+
+impl Analyzer for EntryPointAnalyzer {
+    fn get_name(&self) -> String {
+        "Mach-O entry point analyzer".to_string()
+    }
+
+    fn analyze(&self, ws: &mut Workspace) -> Result<(), Error> {
+        let macho = match Object::parse(&ws.buf) {
+            Ok(Object::Mach(Mach::Binary(macho))) => macho,
+            // the loader mapped the slice matching the workspace's architecture.
+            Ok(Object::Mach(Mach::Fat(fat))) => {
+                let cputype = match ws.loader.get_arch() {
+                    Arch::X32 => cputype::CPU_TYPE_X86,
+                    Arch::X64 => cputype::CPU_TYPE_X86_64,
+                };
+                fat.iter_arches()
+                    .enumerate()
+                    .find(|(_, arch)| matches!(arch, Ok(arch) if arch.cputype == cputype))
+                    .and_then(|(i, _)| fat.get(i).ok())
+                    .ok_or_else(|| failure::err_msg("no matching slice in fat Mach-O"))?
+            }
+            _ => panic!("can't analyze unexpected format"),
+        };
+
+        // goblin resolves LC_MAIN/LC_UNIXTHREAD for us and surfaces the
+        // result as `entry`, an absolute virtual address.
+        let entry = ws
+            .rva(VA::from(macho.entry))
+            .ok_or_else(|| failure::err_msg("entry point is not mapped"))?;
+        debug!("entry point: {}", entry);
+
+        ws.make_symbol(entry, "entry")?;
+        ws.make_function(entry)?;
+        ws.analyze()?;
+
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 use failure::Error;
@@ -25,8 +26,33 @@ impl Default for FlirtConfig {
     }
 }
 
+/// a handful of byte patterns that identify the CRT/startup code emitted by
+/// a particular compiler runtime. these are deliberately small and cheap to
+/// check: they're only used to narrow down *which* signature directory to
+/// load, not to name anything themselves.
+///
+/// patterns are the literal bytes of `__EH_prolog`-style helpers, which are
+/// some of the first routines a CRT startup function calls and which tend
+/// to be extremely stable across versions of a given runtime.
+const STARTUP_SIGNATURES: &[(&str, &[u8])] = &[
+    // __EH_prolog, x86 (see the header comment of the flirt crate's lib.rs for the full function)
+    ("msvcrt", b"\x6A\xFF\x50"),
+    // __EH_prolog3_GS_align / __EH_prolog3_align (x86, newer CRTs)
+    ("ucrt", b"\x51\x8B\x4C\x24\x0C\x89\x5C\x24\x0C"),
+];
+
+/// cheaply scan `buf` for one of the `STARTUP_SIGNATURES`, to guess which
+/// runtime produced this binary (if any) before loading any `.pat`/`.sig`
+/// files.
+fn detect_runtime(buf: &[u8]) -> Option<&'static str> {
+    STARTUP_SIGNATURES
+        .iter()
+        .find(|(_, pattern)| buf.windows(pattern.len()).any(|window| window == *pattern))
+        .map(|&(name, _)| name)
+}
+
 pub struct FlirtAnalyzer {
-    sigs: flirt::FlirtSignatureSet,
+    config: FlirtConfig,
 }
 
 impl FlirtAnalyzer {
@@ -55,16 +81,7 @@ impl FlirtAnalyzer {
                     return false;
                 }
 
-                let wc_count = sig
-                    .byte_sig
-                    .0
-                    .iter()
-                    .take(sig.size_of_function as usize)
-                    .filter(|b| match b {
-                        flirt::SigElement::Wildcard => true,
-                        flirt::SigElement::Byte(_) => false,
-                    })
-                    .count();
+                let wc_count = sig.size_of_function.saturating_sub(sig.specificity());
 
                 if sig.size_of_function < 0x8 {
                     // lancelot specific: don't use signatures for functions less than 0x8 bytes.
@@ -141,18 +158,36 @@ impl FlirtAnalyzer {
         }
         debug!("loaded {} total FLIRT signatures", sigs.len());
 
-        let sigs = FlirtAnalyzer::filter_flirt_signatures(sigs);
-        info!("filtered to {} usable FLIRT signatures", sigs.len());
-
         Ok(sigs)
     }
 
     pub fn new(config: FlirtConfig) -> FlirtAnalyzer {
-        // TODO: add startup signatures to detect runtime/signature set
+        FlirtAnalyzer { config }
+    }
 
-        let mut sigs = vec![];
+    /// run the (cheap) built-in startup-signature detection and, if a
+    /// runtime is recognized, load only its signature subdirectory
+    /// (`<pat_dir>/<runtime>/`, `<sig_dir>/<runtime>/`) rather than every
+    /// `.pat`/`.sig` file under `FlirtConfig`. this both speeds up analysis
+    /// and reduces false-positive matches from unrelated runtimes' signature
+    /// sets.
+    ///
+    /// falls back to loading everything under `pat_dir`/`sig_dir` directly
+    /// when no runtime is recognized.
+    fn load_sigs(&self, buf: &[u8]) -> flirt::FlirtSignatureSet {
+        let (pat_dir, sig_dir) = match detect_runtime(buf) {
+            Some(runtime) => {
+                info!("FLIRT analyzer: detected runtime: {}", runtime);
+                (self.config.pat_dir.join(runtime), self.config.sig_dir.join(runtime))
+            }
+            None => {
+                debug!("FLIRT analyzer: no runtime detected, loading all signature sets");
+                (self.config.pat_dir.clone(), self.config.sig_dir.clone())
+            }
+        };
 
-        for path in [config.pat_dir, config.sig_dir].iter() {
+        let mut sigs = vec![];
+        for path in [pat_dir, sig_dir].iter() {
             sigs.extend(if path.exists() {
                 match FlirtAnalyzer::load_flirt_directory(&path) {
                     Ok(sigs) => sigs,
@@ -164,9 +199,62 @@ impl FlirtAnalyzer {
             });
         }
 
-        FlirtAnalyzer {
-            sigs: flirt::FlirtSignatureSet::with_signatures(sigs),
+        let sigs = FlirtAnalyzer::filter_flirt_signatures(sigs);
+        info!("filtered to {} usable FLIRT signatures", sigs.len());
+
+        flirt::FlirtSignatureSet::with_signatures(sigs)
+    }
+
+    /// resolve the call target of the instruction at `ref_rva`, following
+    /// a near relative CALL to its absolute destination.
+    ///
+    /// returns `None` for anything other than a direct, relative call (e.g.
+    /// an indirect call through a register or memory operand), since those
+    /// don't have a single resolvable target.
+    fn resolve_reference(ws: &Workspace, ref_rva: RVA) -> Option<RVA> {
+        let insn = ws.read_insn(ref_rva).ok()?.x86?;
+        if insn.mnemonic != zydis::Mnemonic::CALL {
+            return None;
         }
+
+        let target = insn.calc_absolute_address(u64::from(ws.va(ref_rva)?), &insn.operands[0]).ok()?;
+        ws.rva(target.into())
+    }
+
+    /// record `name` for `rva` in `applied`, honoring the collision policy:
+    /// the same name must not be applied to more than one distinct location.
+    /// when two signatures disagree about where a name belongs, keep whichever
+    /// signature was more specific (longer / fewer wildcards). `analyze` makes
+    /// the symbols once every match has been recorded.
+    fn apply_name(
+        applied: &mut HashMap<String, (RVA, u16)>,
+        rva: RVA,
+        name: &str,
+        specificity: u16,
+    ) -> Result<(), Error> {
+        match applied.get(name) {
+            None => {
+                applied.insert(name.to_string(), (rva, specificity));
+            }
+            Some(&(existing_rva, existing_specificity)) => {
+                if existing_rva == rva {
+                    // already applied here; nothing to do.
+                } else if specificity > existing_specificity {
+                    debug!(
+                        "FLIRT signature collision: preferring more specific match for {:?} at {} over {}",
+                        name, rva, existing_rva
+                    );
+                    applied.insert(name.to_string(), (rva, specificity));
+                } else {
+                    debug!(
+                        "FLIRT signature collision: not applying {:?} to {} (already applied to more specific match at {})",
+                        name, rva, existing_rva
+                    );
+                }
+            }
+        }
+
+        Ok(())
     }
 }
 
@@ -176,12 +264,20 @@ impl Analyzer for FlirtAnalyzer {
     }
 
     fn analyze(&self, ws: &mut Workspace) -> Result<(), Error> {
+        let sigs = self.load_sigs(&ws.buf);
+
         let mut buf = [0u8; 0xFF];
         let functions: Vec<RVA> = ws.get_functions().cloned().collect();
 
+        // tracks, for each name we've applied, the location and specificity
+        // of the signature that applied it, so that conflicting matches
+        // (the same name at two different locations) can be resolved
+        // deterministically rather than simply overwriting one another.
+        let mut applied: HashMap<String, (RVA, u16)> = HashMap::new();
+
         for &fva in functions.iter() {
             if let Ok(buf) = ws.read_bytes_into(fva, &mut buf[..]) {
-                let matches = self.sigs.r#match(buf);
+                let matches = sigs.r#match(buf);
 
                 // no matches
                 if matches.is_empty() {
@@ -211,17 +307,30 @@ impl Analyzer for FlirtAnalyzer {
 
                 let match_ = matches[0];
 
-                // TODO: should not apply the same symbol name to more than one location?
-                // TODO: apply reference names
-
                 // can unwrap name cause its guaranteed to have a name due to filter above.
                 let name = match_.get_name().unwrap();
                 debug!("FLIRT signature match: {} {}", fva, name);
-                ws.make_symbol(fva, name).unwrap(); // danger
+                FlirtAnalyzer::apply_name(&mut applied, fva, name, match_.specificity())?;
+
+                // propagate names to the callees named by this signature's
+                // reference records, so a single match at the entry of a
+                // statically-linked library names its helpers too.
+                for (offset, ref_name) in match_.get_references() {
+                    let ref_rva = fva + offset as i64;
+                    if let Some(target) = FlirtAnalyzer::resolve_reference(ws, ref_rva) {
+                        trace!("FLIRT reference: {} + {:#x} -> {} ({})", fva, offset, target, ref_name);
+                        FlirtAnalyzer::apply_name(&mut applied, target, ref_name, match_.specificity())?;
+                    }
+                }
+
                 continue;
             }
         }
 
+        for (name, &(rva, _)) in applied.iter() {
+            ws.make_symbol(rva, name)?;
+        }
+
         ws.analyze()?;
 
         Ok(())
@@ -0,0 +1,82 @@
+use failure::Error;
+use log::{debug, trace};
+
+use super::super::{
+    super::{
+        arch::RVA,
+        switchtable::{is_indirect_jmp_through_memory, recover_switch_table, resolve_targets},
+        workspace::Workspace,
+    },
+    Analyzer,
+};
+
+/// recovers the successor basic blocks of an indirect `jmp [reg*scale +
+/// table]` dispatch, which static CALL/JMP xref analysis otherwise misses
+/// entirely.
+pub struct JumpTableAnalyzer {}
+
+impl JumpTableAnalyzer {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> JumpTableAnalyzer {
+        JumpTableAnalyzer {}
+    }
+}
+
+impl Analyzer for JumpTableAnalyzer {
+    fn get_name(&self) -> String {
+        "PE jump table analyzer".to_string()
+    }
+
+    fn analyze(&self, ws: &mut Workspace) -> Result<(), Error> {
+        let functions: Vec<RVA> = ws.get_functions().cloned().collect();
+
+        for &fva in functions.iter() {
+            let bbs = match ws.get_basic_blocks(fva) {
+                Ok(bbs) => bbs,
+                Err(_) => continue,
+            };
+
+            for bb in bbs.iter() {
+                let last = match bb.insns.last() {
+                    Some(&last) => last,
+                    None => continue,
+                };
+
+                if !is_indirect_jmp_through_memory(ws, last) {
+                    continue;
+                }
+                if !bb.successors.is_empty() {
+                    // already resolved, e.g. by the emulator or reloc analysis.
+                    continue;
+                }
+
+                let jmp_index = match bb.insns.iter().position(|&addr| addr == last) {
+                    Some(i) => i,
+                    None => continue,
+                };
+
+                let table = match recover_switch_table(ws, &bb.insns, jmp_index) {
+                    Some(table) => table,
+                    None => continue,
+                };
+
+                let targets = resolve_targets(ws, &table);
+                debug!(
+                    "jump table at {}: {} entries, {} resolved targets",
+                    table.jmp_address,
+                    table.count,
+                    targets.len()
+                );
+
+                for &target in targets.iter() {
+                    trace!("jump table target: {} -> {}", table.jmp_address, target);
+                    ws.make_insn(target)?;
+                }
+            }
+        }
+
+        ws.analyze()?;
+
+        Ok(())
+    }
+}
@@ -5,10 +5,15 @@ use log::{debug, trace, warn};
 use std::ops::Range;
 
 use super::super::{
-    super::{arch::RVA, loader::Permissions, workspace::Workspace},
+    super::{
+        arch::{RVA, VA},
+        loader::Permissions,
+        provenance::Provenance,
+        shadow::Tag,
+        workspace::Workspace,
+    },
     Analyzer,
 };
-use std::collections::HashSet;
 
 #[derive(Debug, Fail)]
 pub enum RelocAnalyzerError {
@@ -16,6 +21,8 @@ pub enum RelocAnalyzerError {
     InvalidRelocType,
     #[fail(display = "Relocation target not found in image")]
     InvalidTargetAddress,
+    #[fail(display = "The given buffer is not a PE or ELF file")]
+    UnsupportedFormat,
 }
 
 pub struct RelocAnalyzer {}
@@ -29,6 +36,13 @@ impl RelocAnalyzer {
 
 // TODO: make this much faster
 fn is_in_insn(ws: &Workspace, rva: RVA) -> bool {
+    // the disassembler tags every instruction body it decodes as it goes
+    // (see `Workspace::mark_insn_provenance`), so the common case is an O(1)
+    // lookup rather than a rescan backwards.
+    if ws.module.provenance.is_in_insn(rva) {
+        return true;
+    }
+
     let start: usize = rva.into();
     // TODO: underflow
     // TODO: remove harded max insn length
@@ -53,6 +67,13 @@ fn is_in_insn(ws: &Workspace, rva: RVA) -> bool {
 }
 
 fn is_ptr(ws: &Workspace, rva: RVA) -> bool {
+    // the relocation engine tags every slot it fixes up as it goes (see
+    // `apply_pe_relocs`/`apply_elf_relocs`), so the common case is an O(1)
+    // lookup rather than re-reading and re-probing the target.
+    if ws.module.provenance.is_pointer_slot(rva) {
+        return true;
+    }
+
     if let Ok(ptr) = ws.read_va(rva) {
         if let Some(ptr) = ws.rva(ptr) {
             return ws.probe(ptr, 1, Permissions::R);
@@ -63,6 +84,13 @@ fn is_ptr(ws: &Workspace, rva: RVA) -> bool {
 }
 
 fn is_zero(ws: &Workspace, rva: RVA) -> bool {
+    // `.bss`-style zero-fill reads as zero but was never actually written:
+    // treat it the same as a real zero so code discovery doesn't mistake
+    // untouched padding for a meaningful zero value or pointer.
+    if !ws.module.provenance.is_initialized(rva) {
+        return true;
+    }
+
     if let Ok(v) = ws.read_u32(rva) {
         return v == 0;
     }
@@ -70,6 +98,14 @@ fn is_zero(ws: &Workspace, rva: RVA) -> bool {
     false
 }
 
+// IMAGE_FILE_MACHINE_* constants that matter for disambiguating the
+// machine-specific reloc type codes 5 and 7 (see `parse_reloc`).
+const IMAGE_FILE_MACHINE_ARM: u16 = 0x01c0;
+const IMAGE_FILE_MACHINE_ARMNT: u16 = 0x01c4; // ARM Thumb-2
+const IMAGE_FILE_MACHINE_RISCV32: u16 = 0x5032;
+const IMAGE_FILE_MACHINE_RISCV64: u16 = 0x5064;
+const IMAGE_FILE_MACHINE_RISCV128: u16 = 0x5128;
+
 #[derive(Debug)]
 pub enum RelocationType {
     ImageRelBasedAbsolute,
@@ -78,16 +114,14 @@ pub enum RelocationType {
     ImageRelBasedHighLow,
     ImageRelBasedHighAdj,
 
-    // ImageRelBasedMIPS_JmpAddr,
-    // ImageRelBasedARM_MOV32,
-    // ImageRelBasedRiscV_High20,
-    ImageRelArch1,
+    ImageRelBasedMIPSJmpAddr,
+    ImageRelBasedArmMov32,
+    ImageRelBasedRiscVHigh20,
 
     ImageRelReserved,
 
-    // ImageRelBasedTHUMB_MOV32,
-    // ImageRelBasedRiscV_Low12I,
-    ImageRelArch2,
+    ImageRelBasedThumbMov32,
+    ImageRelBasedRiscVLow12I,
 
     ImageRelBasedRiscVLow12S,
     ImageRelBasedMIPSJmpAddr16,
@@ -99,7 +133,7 @@ pub struct Reloc {
     pub offset: RVA,
 }
 
-fn parse_reloc(base: RVA, entry: u16) -> Result<Reloc, Error> {
+fn parse_reloc(base: RVA, entry: u16, machine: u16) -> Result<Reloc, Error> {
     let reloc_type = (entry & 0b1111_0000_0000_0000) >> 12;
     let reloc_offset = entry & 0b0000_1111_1111_1111;
 
@@ -110,9 +144,25 @@ fn parse_reloc(base: RVA, entry: u16) -> Result<Reloc, Error> {
         2 => RelocationType::ImageRelBasedLow,
         3 => RelocationType::ImageRelBasedHighLow,
         4 => RelocationType::ImageRelBasedHighAdj,
-        5 => RelocationType::ImageRelArch1,
+        // type 5 is overloaded by machine: MIPS uses it for JMPADDR, ARM for
+        // a MOVW/MOVT pair, RISC-V for the high 20 bits of a U-type immediate.
+        5 => match machine {
+            IMAGE_FILE_MACHINE_ARM => RelocationType::ImageRelBasedArmMov32,
+            IMAGE_FILE_MACHINE_RISCV32 | IMAGE_FILE_MACHINE_RISCV64 | IMAGE_FILE_MACHINE_RISCV128 => {
+                RelocationType::ImageRelBasedRiscVHigh20
+            }
+            _ => RelocationType::ImageRelBasedMIPSJmpAddr,
+        },
         6 => RelocationType::ImageRelReserved,
-        7 => RelocationType::ImageRelArch2,
+        // type 7 is similarly overloaded: ARM Thumb-2 uses it for a MOVW/MOVT
+        // pair, RISC-V for the low 12 bits of an I-type immediate.
+        7 => match machine {
+            IMAGE_FILE_MACHINE_ARMNT => RelocationType::ImageRelBasedThumbMov32,
+            IMAGE_FILE_MACHINE_RISCV32 | IMAGE_FILE_MACHINE_RISCV64 | IMAGE_FILE_MACHINE_RISCV128 => {
+                RelocationType::ImageRelBasedRiscVLow12I
+            }
+            _ => RelocationType::ImageRelReserved,
+        },
         8 => RelocationType::ImageRelBasedRiscVLow12S,
         9 => RelocationType::ImageRelBasedMIPSJmpAddr16,
         10 => RelocationType::ImageRelBasedDir64,
@@ -160,6 +210,8 @@ pub fn get_relocs(ws: &Workspace) -> Result<Vec<Reloc>, Error> {
         _ => return Ok(vec![]),
     };
 
+    let machine = pe.header.coff_header.machine;
+
     let dir_start = RVA::from(reloc_directory.virtual_address as i64);
     let buf = ws.read_bytes(dir_start, reloc_directory.size as usize)?;
 
@@ -187,13 +239,13 @@ pub fn get_relocs(ws: &Workspace) -> Result<Vec<Reloc>, Error> {
             if let Some(&entry) = entries.get(index + i) {
                 let (m, n) = split_u32(entry);
 
-                let reloc1 = parse_reloc(page_rva, m)?;
+                let reloc1 = parse_reloc(page_rva, m, machine)?;
                 if !ws.probe(reloc1.offset, 4, Permissions::R) {
                     break;
                 }
                 ret.push(reloc1);
 
-                let reloc2 = parse_reloc(page_rva, n)?;
+                let reloc2 = parse_reloc(page_rva, n, machine)?;
                 if !ws.probe(reloc2.offset, 4, Permissions::R) {
                     break;
                 }
@@ -209,6 +261,348 @@ pub fn get_relocs(ws: &Workspace) -> Result<Vec<Reloc>, Error> {
     Ok(ret)
 }
 
+/// patch the imm16 carried by an ARM A32 MOVW/MOVT instruction, whose
+/// encoding splits the immediate across bits `[19:16]` (top nibble) and
+/// bits `[11:0]` (bottom 12 bits).
+fn patch_arm_movw_movt(insn: u32, imm16: u16) -> u32 {
+    let imm16 = u32::from(imm16);
+    (insn & 0xFFF0_F000) | ((imm16 & 0xF000) << 4) | (imm16 & 0x0FFF)
+}
+
+fn read_arm_movw_movt_imm16(insn: u32) -> u16 {
+    ((((insn >> 16) & 0xF) << 12) | (insn & 0xFFF)) as u16
+}
+
+/// patch the imm16 carried by a Thumb-2 T3 MOVW/MOVT instruction (a 32-bit,
+/// two-halfword encoding). the immediate is scattered across `imm4:i:imm3:imm8`.
+fn patch_thumb_movw_movt(insn: u32, imm16: u16) -> u32 {
+    let imm16 = u32::from(imm16);
+    let imm4 = (imm16 >> 12) & 0xF;
+    let i = (imm16 >> 11) & 0x1;
+    let imm3 = (imm16 >> 8) & 0x7;
+    let imm8 = imm16 & 0xFF;
+
+    let hw1 = (insn & 0xFFFF) as u32;
+    let hw2 = (insn >> 16) as u32;
+
+    let hw1 = (hw1 & 0xFBF0) | (imm4 << 0) | (i << 10);
+    let hw2 = (hw2 & 0x8F00) | (imm3 << 12) | imm8;
+
+    hw1 | (hw2 << 16)
+}
+
+fn read_thumb_movw_movt_imm16(insn: u32) -> u16 {
+    let hw1 = insn & 0xFFFF;
+    let hw2 = insn >> 16;
+
+    let imm4 = hw1 & 0xF;
+    let i = (hw1 >> 10) & 0x1;
+    let imm3 = (hw2 >> 12) & 0x7;
+    let imm8 = hw2 & 0xFF;
+
+    (((imm4 << 12) | (i << 11) | (imm3 << 8) | imm8) as u16) & 0xFFFF
+}
+
+/// patch the 20-bit upper immediate of a RISC-V U-type instruction (`lui`,
+/// `auipc`), which occupies bits `[31:12]`.
+fn patch_riscv_hi20(insn: u32, hi20: u32) -> u32 {
+    (insn & 0x0000_0FFF) | (hi20 << 12)
+}
+
+/// patch the 12-bit immediate of a RISC-V I-type instruction (e.g. `addi`),
+/// which occupies bits `[31:20]`.
+fn patch_riscv_lo12_i(insn: u32, lo12: u32) -> u32 {
+    (insn & 0x000F_FFFF) | ((lo12 & 0xFFF) << 20)
+}
+
+/// patch the 12-bit immediate of a RISC-V S-type instruction (e.g. `sw`),
+/// which is split across bits `[31:25]` and `[11:7]`.
+fn patch_riscv_lo12_s(insn: u32, lo12: u32) -> u32 {
+    let lo12 = lo12 & 0xFFF;
+    let hi7 = (lo12 >> 5) & 0x7F;
+    let lo5 = lo12 & 0x1F;
+    (insn & 0x01FF_F07F) | (hi7 << 25) | (lo5 << 7)
+}
+
+/// the 4-bit `IMAGE_REL_BASED_*` type nibble that a `RelocationType` was
+/// originally parsed from (see `parse_reloc`). needed to reconstruct the raw
+/// 16-bit adjustment word that follows a HIGHADJ entry, since `get_relocs`
+/// otherwise parses every table slot uniformly as `type:offset`.
+fn reloc_type_nibble(typ: &RelocationType) -> u16 {
+    match typ {
+        RelocationType::ImageRelBasedAbsolute => 0,
+        RelocationType::ImageRelBasedHigh => 1,
+        RelocationType::ImageRelBasedLow => 2,
+        RelocationType::ImageRelBasedHighLow => 3,
+        RelocationType::ImageRelBasedHighAdj => 4,
+        RelocationType::ImageRelBasedMIPSJmpAddr
+        | RelocationType::ImageRelBasedArmMov32
+        | RelocationType::ImageRelBasedRiscVHigh20 => 5,
+        RelocationType::ImageRelReserved => 6,
+        RelocationType::ImageRelBasedThumbMov32 | RelocationType::ImageRelBasedRiscVLow12I => 7,
+        RelocationType::ImageRelBasedRiscVLow12S => 8,
+        RelocationType::ImageRelBasedMIPSJmpAddr16 => 9,
+        RelocationType::ImageRelBasedDir64 => 10,
+    }
+}
+
+/// apply each relocation in `relocs` to `ws`, rewriting the target bytes as
+/// if the module had been loaded at `new_base` rather than its preferred
+/// base address. returns the fixed-up targets of the HIGHLOW/DIR64 entries
+/// that map into the module; they are not filtered by section permissions.
+///
+/// `delta` is `new_base - preferred_base`: the amount every relocatable
+/// pointer must be shifted to remain correct.
+fn apply_pe_relocs(ws: &mut Workspace, relocs: &[Reloc], delta: i64) -> Result<Vec<RVA>, Error> {
+    let mut pending_highadj: Option<RVA> = None;
+    let mut code_targets = vec![];
+
+    for reloc in relocs.iter() {
+        if let Some(highadj_offset) = pending_highadj.take() {
+            // base relocation blocks are always 4KB-page-aligned, so the
+            // HIGHADJ entry and its adjustment-word successor share a page.
+            let page_rva = usize::from(highadj_offset) & !0xFFF;
+            let adj_offset12 = (usize::from(reloc.offset) - page_rva) as u32 & 0xFFF;
+            let adj = (reloc_type_nibble(&reloc.typ) << 12) | adj_offset12 as u16;
+
+            let existing = (u32::from(ws.read_u16(highadj_offset)?) << 16) | u32::from(adj);
+            let fixed = (existing as i64).wrapping_add(delta) as u32;
+            // round the high word up if the low word's addition would have
+            // carried into it.
+            let rounded = ((fixed as u64) + 0x8000) >> 16;
+            ws.write_u16(highadj_offset, rounded as u16)?;
+            continue;
+        }
+
+        match reloc.typ {
+            RelocationType::ImageRelBasedAbsolute => {
+                // padding entry; no fixup to apply.
+            }
+            RelocationType::ImageRelBasedHigh => {
+                let existing = u32::from(ws.read_u16(reloc.offset)?) << 16;
+                let fixed = (existing as i64).wrapping_add(delta) as u32;
+                ws.write_u16(reloc.offset, (fixed >> 16) as u16)?;
+            }
+            RelocationType::ImageRelBasedLow => {
+                let existing = u32::from(ws.read_u16(reloc.offset)?);
+                let fixed = (existing as i64).wrapping_add(delta) as u32;
+                ws.write_u16(reloc.offset, (fixed & 0xFFFF) as u16)?;
+            }
+            RelocationType::ImageRelBasedHighAdj => {
+                // the following entry in the table is not a separate fixup:
+                // its offset field instead carries the low-order adjustment
+                // word for this HIGHADJ entry.
+                pending_highadj = Some(reloc.offset);
+                continue;
+            }
+            RelocationType::ImageRelBasedHighLow => {
+                let existing = ws.read_u32(reloc.offset)?;
+                let fixed = (existing as i64).wrapping_add(delta) as u32;
+                ws.write_u32(reloc.offset, fixed)?;
+                ws.module
+                    .provenance
+                    .mark_range(reloc.offset, reloc.offset + 4i64, Provenance::Pointer)?;
+                ws.shadow.tag_range(reloc.offset, reloc.offset + 4i64, Tag::Pointer)?;
+
+                if let Some(target) = ws.rva(VA::from(fixed)) {
+                    code_targets.push(target);
+                }
+            }
+            RelocationType::ImageRelBasedDir64 => {
+                let existing = ws.read_u64(reloc.offset)?;
+                let fixed = (existing as i64).wrapping_add(delta) as u64;
+                ws.write_u64(reloc.offset, fixed)?;
+                ws.module
+                    .provenance
+                    .mark_range(reloc.offset, reloc.offset + 8i64, Provenance::Pointer)?;
+                ws.shadow.tag_range(reloc.offset, reloc.offset + 8i64, Tag::Pointer)?;
+
+                if let Some(target) = ws.rva(VA::from(fixed)) {
+                    code_targets.push(target);
+                }
+            }
+            RelocationType::ImageRelBasedArmMov32 => {
+                let movw = ws.read_u32(reloc.offset)?;
+                let movt = ws.read_u32(reloc.offset + 4i64)?;
+
+                let lo16 = read_arm_movw_movt_imm16(movw);
+                let hi16 = read_arm_movw_movt_imm16(movt);
+                let existing = (u32::from(hi16) << 16) | u32::from(lo16);
+                let fixed = (existing as i64).wrapping_add(delta) as u32;
+
+                ws.write_u32(reloc.offset, patch_arm_movw_movt(movw, (fixed & 0xFFFF) as u16))?;
+                ws.write_u32(reloc.offset + 4i64, patch_arm_movw_movt(movt, (fixed >> 16) as u16))?;
+            }
+            RelocationType::ImageRelBasedThumbMov32 => {
+                let movw = ws.read_u32(reloc.offset)?;
+                let movt = ws.read_u32(reloc.offset + 4i64)?;
+
+                let lo16 = read_thumb_movw_movt_imm16(movw);
+                let hi16 = read_thumb_movw_movt_imm16(movt);
+                let existing = (u32::from(hi16) << 16) | u32::from(lo16);
+                let fixed = (existing as i64).wrapping_add(delta) as u32;
+
+                ws.write_u32(reloc.offset, patch_thumb_movw_movt(movw, (fixed & 0xFFFF) as u16))?;
+                ws.write_u32(reloc.offset + 4i64, patch_thumb_movw_movt(movt, (fixed >> 16) as u16))?;
+            }
+            RelocationType::ImageRelBasedRiscVHigh20 => {
+                let insn = ws.read_u32(reloc.offset)?;
+                let hi20 = ((insn >> 12) as i64).wrapping_add(delta >> 12) as u32;
+                ws.write_u32(reloc.offset, patch_riscv_hi20(insn, hi20))?;
+            }
+            RelocationType::ImageRelBasedRiscVLow12I => {
+                let insn = ws.read_u32(reloc.offset)?;
+                let lo12 = (((insn >> 20) as i64).wrapping_add(delta)) as u32;
+                ws.write_u32(reloc.offset, patch_riscv_lo12_i(insn, lo12))?;
+            }
+            RelocationType::ImageRelBasedRiscVLow12S => {
+                let insn = ws.read_u32(reloc.offset)?;
+                let existing = (((insn >> 25) & 0x7F) << 5) | ((insn >> 7) & 0x1F);
+                let lo12 = ((existing as i64).wrapping_add(delta)) as u32;
+                ws.write_u32(reloc.offset, patch_riscv_lo12_s(insn, lo12))?;
+            }
+            RelocationType::ImageRelBasedMIPSJmpAddr | RelocationType::ImageRelBasedMIPSJmpAddr16 => {
+                warn!("MIPS relocations are not yet supported: {:?}", reloc.offset);
+            }
+            RelocationType::ImageRelReserved => {
+                warn!("ignoring reserved relocation at {:?}", reloc.offset);
+            }
+        }
+    }
+
+    Ok(code_targets)
+}
+
+/// apply every supported ELF relocation (`R_X86_64_RELATIVE`,
+/// `R_X86_64_64`, `R_386_32`) found in `REL`/`RELA` tables, rewriting the
+/// fixed-up pointer in place.
+fn apply_elf_relocs(ws: &mut Workspace, new_base: VA) -> Result<Vec<RVA>, Error> {
+    use goblin::elf::reloc::{R_386_32, R_X86_64_64, R_X86_64_RELATIVE};
+
+    let elf = match Object::parse(&ws.buf) {
+        Ok(Object::Elf(elf)) => elf,
+        _ => return Ok(vec![]),
+    };
+
+    let base: u64 = new_base.into();
+    let mut code_targets = vec![];
+
+    let relocs = elf.dynrelas.iter().chain(elf.dynrels.iter()).chain(elf.pltrelocs.iter());
+
+    for reloc in relocs {
+        let offset = match ws.rva(VA::from(reloc.r_offset)) {
+            Some(offset) => offset,
+            None => continue,
+        };
+
+        // `R_X86_64_64` and `R_386_32` are both numerically `1`; the ELF
+        // class (32- vs 64-bit, `elf.is_64`), not the reloc type alone,
+        // says which one a given `r_type` means here, and therefore
+        // whether the fixup is 4 or 8 bytes wide.
+        let value = if elf.is_64 {
+            match reloc.r_type {
+                R_X86_64_RELATIVE => base.wrapping_add(reloc.r_addend.unwrap_or(0) as u64),
+                R_X86_64_64 => {
+                    let sym = match elf.dynsyms.get(reloc.r_sym) {
+                        Some(sym) => sym,
+                        None => continue,
+                    };
+                    sym.st_value.wrapping_add(reloc.r_addend.unwrap_or(0) as u64)
+                }
+                other => {
+                    warn!("ignoring relocation with unsupported type: {}", other);
+                    continue;
+                }
+            }
+        } else {
+            match reloc.r_type {
+                R_386_32 => {
+                    let sym = match elf.dynsyms.get(reloc.r_sym) {
+                        Some(sym) => sym,
+                        None => continue,
+                    };
+                    sym.st_value.wrapping_add(reloc.r_addend.unwrap_or(0) as u64)
+                }
+                other => {
+                    warn!("ignoring relocation with unsupported type: {}", other);
+                    continue;
+                }
+            }
+        };
+
+        if elf.is_64 {
+            ws.write_u64(offset, value)?;
+            ws.module.provenance.mark_range(offset, offset + 8i64, Provenance::Pointer)?;
+            ws.shadow.tag_range(offset, offset + 8i64, Tag::Pointer)?;
+        } else {
+            ws.write_u32(offset, value as u32)?;
+            ws.module.provenance.mark_range(offset, offset + 4i64, Provenance::Pointer)?;
+            ws.shadow.tag_range(offset, offset + 4i64, Tag::Pointer)?;
+        }
+
+        if let Some(target) = ws.rva(VA::from(value)) {
+            code_targets.push(target);
+        }
+    }
+
+    Ok(code_targets)
+}
+
+/// rewrite every relocatable pointer in `ws.module.address_space` as if the
+/// module had been loaded at `new_base`, record every fixed-up location
+/// that lands in an executable section as a discovered instruction start,
+/// and return those code targets.
+pub fn apply_relocs(ws: &mut Workspace, new_base: VA) -> Result<Vec<RVA>, Error> {
+    let code_targets = match Object::parse(&ws.buf) {
+        Ok(Object::PE(pe)) => {
+            let preferred_base = pe
+                .header
+                .optional_header
+                .ok_or(RelocAnalyzerError::UnsupportedFormat)?
+                .windows_fields
+                .image_base;
+            let actual_base: u64 = new_base.into();
+            let delta = (actual_base as i64).wrapping_sub(preferred_base as i64);
+
+            let relocs = get_relocs(ws)?;
+            apply_pe_relocs(ws, &relocs, delta)?
+        }
+        Ok(Object::Elf(_)) => apply_elf_relocs(ws, new_base)?,
+        _ => return Err(RelocAnalyzerError::UnsupportedFormat.into()),
+    };
+
+    let x_sections: Vec<Range<RVA>> = ws
+        .module
+        .sections
+        .iter()
+        .filter(|section| section.perms.intersects(Permissions::X))
+        .map(|section| Range {
+            start: section.addr,
+            end:   section.end(),
+        })
+        .collect();
+
+    let code_targets: Vec<RVA> = code_targets
+        .into_iter()
+        .filter(|&rva| sections_contain(&x_sections, rva))
+        .filter(|&rva| !is_in_insn(ws, rva))
+        .filter(|&rva| !is_ptr(ws, rva))
+        .filter(|&rva| !is_zero(ws, rva))
+        .collect();
+
+    for &rva in code_targets.iter() {
+        debug!("found pointer via relocation fixup to {} (code)", rva);
+        ws.make_insn(rva)?;
+        ws.analyze()?;
+        // best-effort: a failure to decode here doesn't invalidate the
+        // `make_insn`/`analyze` above, it just means this byte range stays
+        // untagged for `is_in_insn`'s fast path.
+        let _ = ws.mark_insn_provenance(rva);
+    }
+
+    Ok(code_targets)
+}
+
 fn sections_contain(sections: &[Range<RVA>], rva: RVA) -> bool {
     sections.iter().any(|section| section.contains(&rva))
 }
@@ -247,18 +641,7 @@ impl Analyzer for RelocAnalyzer {
             })
             .collect();
 
-        let relocs: Vec<Reloc> = get_relocs(ws)?
-            .into_iter()
-            .filter(|r| match &r.typ {
-                RelocationType::ImageRelBasedHighLow => true,
-                RelocationType::ImageRelBasedDir64 => true,
-                reloc_type => {
-                    // all other reloc types are currently unsupported (uncommon)
-                    warn!("ignoring relocation with unsupported type: {:?}", reloc_type);
-                    false
-                }
-            })
-            .collect();
+        let relocs: Vec<Reloc> = get_relocs(ws)?;
         debug!("found {} total relocs", relocs.len());
 
         for reloc in relocs.iter() {
@@ -297,59 +680,26 @@ impl Analyzer for RelocAnalyzer {
             );
         }
 
-        // scan for relocations to code.
-        //
-        // a relocation is a hardcoded offset that must be fixed up if the desired base
-        // address  cannot be used.
-        // for example, the function pointer passed to CreateThread will be a hardcoded
-        // address  of the start of the function.
-        //
-        // relocated pointers may point to the .data section, e.g. strings or other
-        // constants. we want to ignore these.
-        // we are only interested in targets in executable sections.
-        // we assume these are pointers to instructions/code.
+        // actually apply the relocations -- rewriting each fixed-up pointer
+        // in place for the module's current `base_address` -- rather than
+        // only validating that the existing (pre-relocation) pointers are
+        // sane. `apply_relocs` is what recovers code reached only via a
+        // relocated pointer (e.g. the function pointer passed to
+        // `CreateThread`): relocated pointers may also land in `.data`
+        // (strings, other constants), so it restricts itself to targets in
+        // executable sections and skips ones that already look like an
+        // instruction or another pointer.
         //
-        // looking for pointers into the .text section
-        // to things that
-        //   1. are not already in an instruction
-        //   2. don't appear to be a pointer
-        // and assume this is code.
-
-        let mut unique_targets: HashSet<RVA> = HashSet::new();
-        unique_targets.extend(
-            relocs
-                .iter()
-                .map(|reloc| reloc.offset)
-                .map(|rva| ws.read_va(rva))
-                .filter_map(Result::ok)
-                .filter_map(|va| ws.rva(va)),
-        );
-
-        debug!("reduced to {} unique reloc targets", unique_targets.len());
-
-        let o: Vec<RVA> = unique_targets
-            .iter()
-            .filter(|&&rva| sections_contain(&x_sections, rva))
-            .filter(|&&rva| !is_in_insn(ws, rva))
-            .filter(|&&rva| !is_ptr(ws, rva))
-            .filter(|&&rva| !is_zero(ws, rva))
-            .copied()
-            // TODO: maybe ensure that the insn decodes.
-            .collect();
-
-        debug!("found {} relocs that point to instructions", o.len());
-
-        o.iter().for_each(|&rva| {
-            debug!(
-                "found pointer via relocations from executable section to {} (code)",
-                rva
-            );
+        // a workspace built from a raw, non-PE/ELF image (e.g. shellcode)
+        // has nothing for this analyzer to do.
+        match apply_relocs(ws, ws.module.base_address) {
+            Ok(code_targets) => debug!("applied relocs, found {} relocs that point to instructions", code_targets.len()),
+            Err(e) => match e.downcast_ref::<RelocAnalyzerError>() {
+                Some(RelocAnalyzerError::UnsupportedFormat) => debug!("reloc analyzer: no relocatable format found"),
+                _ => return Err(e),
+            },
+        }
 
-            // TODO: consume result
-            ws.make_insn(rva).unwrap();
-            // TODO: consume result
-            ws.analyze().unwrap();
-        });
         Ok(())
     }
 }
@@ -0,0 +1,170 @@
+use byteorder::{ByteOrder, LittleEndian};
+use failure::Error;
+use goblin::Object;
+use log::{debug, trace, warn};
+
+use super::super::{
+    super::{arch::RVA, loader::Permissions, workspace::Workspace},
+    Analyzer,
+};
+
+/// the `UNW_FLAG_CHAININFO` bit within `UnwindInfo.Flags`.
+///
+/// when set, the unwind codes are followed by another `RUNTIME_FUNCTION`
+///  entry describing the parent frame, rather than a language-specific
+///  exception handler.
+const UNW_FLAG_CHAININFO: u8 = 0x4;
+
+/// a single entry from the `.pdata` exception directory (x64).
+///
+/// see: https://docs.microsoft.com/en-us/cpp/build/exception-handling-x64
+#[derive(Debug, Clone, Copy)]
+pub struct RuntimeFunction {
+    pub begin_address:       RVA,
+    pub end_address:         RVA,
+    pub unwind_info_address: RVA,
+}
+
+/// parse the `IMAGE_DIRECTORY_ENTRY_EXCEPTION` directory into its
+/// constituent `RUNTIME_FUNCTION` records.
+///
+/// each record is 12 bytes: `{BeginAddress, EndAddress, UnwindInfoAddress}`,
+/// all relative virtual addresses.
+pub fn get_runtime_functions(ws: &Workspace) -> Result<Vec<RuntimeFunction>, Error> {
+    let pe = match Object::parse(&ws.buf) {
+        Ok(Object::PE(pe)) => pe,
+        _ => return Ok(vec![]),
+    };
+
+    let opt_header = match pe.header.optional_header {
+        Some(opt_header) => opt_header,
+        _ => return Ok(vec![]),
+    };
+
+    let exception_directory = match opt_header.data_directories.get_exception_table() {
+        Some(exception_directory) => exception_directory,
+        _ => return Ok(vec![]),
+    };
+
+    let dir_start = RVA::from(exception_directory.virtual_address as i64);
+    let buf = ws.read_bytes(dir_start, exception_directory.size as usize)?;
+
+    let mut ret = vec![];
+    for entry in buf.chunks_exact(0xC) {
+        let begin_address = LittleEndian::read_u32(&entry[0x0..0x4]);
+        let end_address = LittleEndian::read_u32(&entry[0x4..0x8]);
+        let unwind_info_address = LittleEndian::read_u32(&entry[0x8..0xC]);
+
+        if begin_address == 0 && end_address == 0 && unwind_info_address == 0 {
+            // padding at the end of the directory.
+            continue;
+        }
+
+        ret.push(RuntimeFunction {
+            begin_address:       RVA::from(begin_address as i64),
+            end_address:         RVA::from(end_address as i64),
+            unwind_info_address: RVA::from(unwind_info_address as i64),
+        });
+    }
+
+    Ok(ret)
+}
+
+/// follow the `UNW_FLAG_CHAININFO` chain from the given unwind info to the
+/// `RUNTIME_FUNCTION` describing the parent frame, if any.
+fn get_chained_parent(ws: &Workspace, rf: &RuntimeFunction) -> Option<RuntimeFunction> {
+    let flags_and_version = ws.read_u8(rf.unwind_info_address).ok()?;
+    let flags = flags_and_version >> 3;
+    if flags & UNW_FLAG_CHAININFO == 0 {
+        return None;
+    }
+
+    let count_of_codes = ws.read_u8(rf.unwind_info_address + 2i64).ok()? as u64;
+
+    // header (4 bytes) + codes (2 bytes each, rounded up to an even count for alignment).
+    let codes_size = ((count_of_codes + (count_of_codes & 1)) * 2) as i64;
+    let chained_rva = rf.unwind_info_address + 4i64 + codes_size;
+
+    let begin_address = ws.read_u32(chained_rva).ok()?;
+    let end_address = ws.read_u32(chained_rva + 4i64).ok()?;
+    let unwind_info_address = ws.read_u32(chained_rva + 8i64).ok()?;
+
+    Some(RuntimeFunction {
+        begin_address:       RVA::from(begin_address as i64),
+        end_address:         RVA::from(end_address as i64),
+        unwind_info_address: RVA::from(unwind_info_address as i64),
+    })
+}
+
+/// recovers functions that are only reachable via a `.pdata`/exception
+/// directory entry, and have no direct CALL xref.
+///
+/// for example, in k32.dll, `0x1800012d4` is only called indirectly via a
+/// function pointer, so neither `EntryPointAnalyzer` nor `ExportsAnalyzer`
+/// discover it; however it does have a `RUNTIME_FUNCTION` entry describing
+/// its unwind info, which this analyzer uses to recover it as a function.
+pub struct RuntimeFunctionAnalyzer {}
+
+impl RuntimeFunctionAnalyzer {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> RuntimeFunctionAnalyzer {
+        RuntimeFunctionAnalyzer {}
+    }
+}
+
+impl Analyzer for RuntimeFunctionAnalyzer {
+    fn get_name(&self) -> String {
+        "PE RUNTIME_FUNCTION analyzer".to_string()
+    }
+
+    /// ```
+    /// use lancelot::rsrc::*;
+    /// use lancelot::arch::*;
+    /// use lancelot::analysis::Analyzer;
+    /// use lancelot::workspace::Workspace;
+    /// use lancelot::analysis::pe::RuntimeFunctionAnalyzer;
+    /// lancelot::test::init_logging();
+    ///
+    /// let mut ws = Workspace::from_bytes("k32.dll", &get_buf(Rsrc::K32))
+    ///    .disable_analysis()
+    ///    .load().unwrap();
+    ///
+    /// let anal = RuntimeFunctionAnalyzer::new();
+    /// anal.analyze(&mut ws).unwrap();
+    ///
+    /// assert!(ws.get_meta(RVA(0x12d4)).unwrap().is_insn());
+    /// ```
+    fn analyze(&self, ws: &mut Workspace) -> Result<(), Error> {
+        let runtime_functions = get_runtime_functions(ws)?;
+        debug!("found {} RUNTIME_FUNCTION entries", runtime_functions.len());
+
+        for rf in runtime_functions.iter() {
+            if !ws.probe(rf.begin_address, 1, Permissions::X) {
+                warn!("RUNTIME_FUNCTION begin address not executable: {}", rf.begin_address);
+                continue;
+            }
+
+            trace!("found function via RUNTIME_FUNCTION: {}", rf.begin_address);
+            ws.make_function(rf.begin_address)?;
+            ws.make_symbol(rf.begin_address, &format!("sub_{}", rf.begin_address))
+                .ok();
+
+            // a chained unwind info describes a fragment of a larger, logically
+            // non-contiguous function; its parent is also a real function start.
+            let mut current = *rf;
+            while let Some(parent) = get_chained_parent(ws, &current) {
+                if !ws.probe(parent.begin_address, 1, Permissions::X) {
+                    break;
+                }
+
+                trace!("found parent function via chained unwind info: {}", parent.begin_address);
+                ws.make_function(parent.begin_address)?;
+                current = parent;
+            }
+        }
+
+        ws.analyze()?;
+
+        Ok(())
+    }
+}
@@ -8,6 +8,7 @@ const PAGE_SIZE: usize = 0x1000;
 #[derive(Debug)]
 pub enum Error {
     NotMapped,
+    BufferSize,
 }
 
 fn page(rva: RVA) -> usize {
@@ -44,8 +45,56 @@ impl<T: Default + Copy> Default for Page<T> {
     }
 }
 
+/// a handle to a `Page` bump-allocated out of a `PageArena`.
+/// opaque outside of this module: the only thing you can do with one is
+/// hand it back to the arena that minted it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PageHandle(usize);
+
+/// a simple bump allocator for `Page<T>`s.
+///
+/// mapping a multi-gigabyte image used to mean allocating (and zeroing) a
+/// `Vec` slot sized for *every potential page* up front (one
+/// `Option<Page<T>>`, whether or not the page is ever mapped), which is
+/// wasteful in both memory and cache locality for large, sparsely-mapped
+/// images.
+///
+/// instead, pages are pushed onto a single contiguous `Vec<Page<T>>` as
+/// they're mapped (so allocation count scales with *mapped* pages, not
+/// address space size), and everything else just stores a small `usize`
+/// handle into this arena.
+struct PageArena<T: Default + Copy> {
+    pages: Vec<Page<T>>,
+}
+
+impl<T: Default + Copy> PageArena<T> {
+    fn new() -> PageArena<T> {
+        PageArena { pages: Vec::new() }
+    }
+
+    fn alloc(&mut self, items: &[T]) -> PageHandle {
+        self.pages.push(Page::new(items));
+        PageHandle(self.pages.len() - 1)
+    }
+
+    fn alloc_default(&mut self) -> PageHandle {
+        self.pages.push(Page::default());
+        PageHandle(self.pages.len() - 1)
+    }
+
+    fn get(&self, handle: PageHandle) -> &Page<T> {
+        &self.pages[handle.0]
+    }
+
+    fn get_mut(&mut self, handle: PageHandle) -> &mut Page<T> {
+        &mut self.pages[handle.0]
+    }
+}
+
 pub struct DenseAddressSpace<T: Default + Copy> {
-    pages: Vec<Option<Page<T>>>
+    arena: PageArena<T>,
+    // index into `arena`, keyed by page number. `None` means unmapped.
+    pages: Vec<Option<PageHandle>>,
 }
 
 impl<T: Default + Copy> DenseAddressSpace<T> {
@@ -55,7 +104,8 @@ impl<T: Default + Copy> DenseAddressSpace<T> {
         pages.resize_with(page_count, || None);
 
         DenseAddressSpace {
-            pages
+            arena: PageArena::new(),
+            pages,
         }
     }
 
@@ -75,7 +125,8 @@ impl<T: Default + Copy> DenseAddressSpace<T> {
             return Err(Error::NotMapped);
         }
 
-        self.pages[page(rva)] = Some(Page::new(items));
+        let handle = self.arena.alloc(items);
+        self.pages[page(rva)] = Some(handle);
 
         Ok(())
     }
@@ -149,15 +200,14 @@ impl<T: Default + Copy> DenseAddressSpace<T> {
             return None;
         }
 
-        let page = match &self.pages[page(rva)] {
+        let handle = match self.pages[page(rva)] {
             // page is not mapped
             None => return None,
             // page is mapped
-            Some(page) => page,
+            Some(handle) => handle,
         };
 
-        Some(page.elements[page_offset(rva)])
-
+        Some(self.arena.get(handle).elements[page_offset(rva)])
     }
 
     /// handle the simple slice case: when start and end fall within the same page.
@@ -172,17 +222,8 @@ impl<T: Default + Copy> DenseAddressSpace<T> {
     /// assert_eq!(d.slice(0x0.into(), 0x2.into()).unwrap(), [0x0, 0x0]);
     /// ```
     fn slice_simple(&self, start: RVA, end: RVA) -> Result<Vec<T>, Error> {
-        if page(start) > self.pages.len() {
-            return Err(Error::NotMapped);
-        }
-
-        let page = match &self.pages[page(start)] {
-            // page is not mapped
-            None => return Err(Error::NotMapped),
-            // page is mapped
-            Some(page) => page,
-        };
-
+        let handle = self.page_handle(start)?;
+        let page = self.arena.get(handle);
         Ok(page.elements[page_offset(start)..page_offset(end)].to_vec())
     }
 
@@ -244,7 +285,7 @@ impl<T: Default + Copy> DenseAddressSpace<T> {
 
         // one.
         {
-            let page = self.pages[page(start)].as_ref().unwrap();
+            let page = self.arena.get(self.page_handle(start)?);
             let buf = &page.elements[page_offset(start)..];
             ret.extend_from_slice(buf);
         }
@@ -254,7 +295,7 @@ impl<T: Default + Copy> DenseAddressSpace<T> {
             let start_index = page(start) + 1;
             let end_index = page(end);
             for page_index in start_index..end_index {
-                let page = self.pages[page_index].as_ref().unwrap();
+                let page = self.arena.get(self.page_handle((page_index * PAGE_SIZE).into())?);
                 let buf = &page.elements[..];
                 ret.extend_from_slice(buf);
             }
@@ -262,7 +303,7 @@ impl<T: Default + Copy> DenseAddressSpace<T> {
 
         // three.
         if page_offset(end) != 0x0 {
-            let page = self.pages[page(end)].as_ref().unwrap();
+            let page = self.arena.get(self.page_handle(end)?);
             let buf = &page.elements[..page_offset(end)];
             ret.extend_from_slice(buf);
         }
@@ -289,5 +330,128 @@ impl<T: Default + Copy> DenseAddressSpace<T> {
         }
     }
 
-    // TODO: slice_into
+    /// zero-copy variant of `slice`: copies `end - start` items into the
+    /// caller-provided buffer, rather than allocating a fresh `Vec`.
+    ///
+    /// this matters on hot paths that read small, fixed-size regions over
+    /// and over (e.g. the FLIRT analyzer reading candidate function
+    /// prologues): reusing a stack buffer across calls avoids an allocation
+    /// (and a memcpy into it) per read.
+    ///
+    /// errors:
+    ///   - Error::BufferSize: if `buf.len() != end - start`
+    ///   - Error::NotMapped: if any requested address is not mapped
+    ///
+    /// panic if:
+    ///   - start > end
+    ///
+    /// ```
+    /// use lancelot::arch::RVA;
+    /// use lancelot::aspace::DenseAddressSpace;
+    ///
+    /// let mut d: DenseAddressSpace<u8> = DenseAddressSpace::with_capacity(0x2000.into());
+    /// d.map(0x0.into(), &[0x41; 0x1000]).expect("failed to map");
+    ///
+    /// let mut buf = [0u8; 0x4];
+    /// d.slice_into(0x0.into(), 0x4.into(), &mut buf).expect("failed to read");
+    /// assert_eq!(buf, [0x41, 0x41, 0x41, 0x41]);
+    /// ```
+    pub fn slice_into(&self, start: RVA, end: RVA, buf: &mut [T]) -> Result<(), Error> {
+        if start > end {
+            panic!("start > end");
+        }
+
+        let want: usize = (end - start).into();
+        if buf.len() != want {
+            return Err(Error::BufferSize);
+        }
+
+        if page(start) == page(end) {
+            let handle = self.page_handle(start)?;
+            let page = self.arena.get(handle);
+            buf.copy_from_slice(&page.elements[page_offset(start)..page_offset(end)]);
+            return Ok(());
+        }
+
+        // ensure each page within the requested region is mapped, up front,
+        // so we don't leave `buf` partially written on a later failure.
+        let start_page = page(start);
+        let end_page = if page_offset(end) == 0 { page(end) - 1 } else { page(end) };
+        for p in start_page..=end_page {
+            if self.page_handle((p * PAGE_SIZE).into()).is_err() {
+                return Err(Error::NotMapped);
+            }
+        }
+
+        let mut written = 0usize;
+
+        // one: from `start` to the end of its page.
+        {
+            let page = self.arena.get(self.page_handle(start)?);
+            let src = &page.elements[page_offset(start)..];
+            buf[written..written + src.len()].copy_from_slice(src);
+            written += src.len();
+        }
+
+        // two: any intermediate complete pages.
+        if page(start) != page(end) - 1 {
+            for page_index in (page(start) + 1)..page(end) {
+                let page = self.arena.get(self.page_handle((page_index * PAGE_SIZE).into())?);
+                buf[written..written + PAGE_SIZE].copy_from_slice(&page.elements[..]);
+                written += PAGE_SIZE;
+            }
+        }
+
+        // three: from the start of the final page until `end`.
+        if page_offset(end) != 0x0 {
+            let page = self.arena.get(self.page_handle(end)?);
+            let src = &page.elements[..page_offset(end)];
+            buf[written..written + src.len()].copy_from_slice(src);
+        }
+
+        Ok(())
+    }
+
+    /// set a single item at the given address, lazily mapping its page
+    /// (to the default value) if its not already mapped.
+    ///
+    /// this is the counterpart to `get` for address spaces that are
+    /// populated incrementally, byte by byte, rather than via bulk `map`
+    /// calls (e.g. the shadow/tag address space).
+    ///
+    /// ```
+    /// use lancelot::arch::RVA;
+    /// use lancelot::aspace::DenseAddressSpace;
+    ///
+    /// let mut d: DenseAddressSpace<u32> = DenseAddressSpace::with_capacity(0x2000.into());
+    /// assert_eq!(d.get(0x0.into()), None);
+    ///
+    /// d.set(0x0.into(), 0x41).expect("failed to set");
+    /// assert_eq!(d.get(0x0.into()), Some(0x41));
+    /// assert_eq!(d.get(0x1.into()), Some(0x0), "rest of the page is lazily zeroed");
+    /// ```
+    pub fn set(&mut self, rva: RVA, value: T) -> Result<(), Error> {
+        if page(rva) > self.pages.len() {
+            return Err(Error::NotMapped);
+        }
+
+        if self.pages[page(rva)].is_none() {
+            self.pages[page(rva)] = Some(self.arena.alloc_default());
+        }
+
+        let handle = self.pages[page(rva)].unwrap();
+        self.arena.get_mut(handle).elements[page_offset(rva)] = value;
+
+        Ok(())
+    }
+
+    /// fetch the arena handle backing the page containing `rva`, or
+    /// `Error::NotMapped` if its unmapped.
+    fn page_handle(&self, rva: RVA) -> Result<PageHandle, Error> {
+        if page(rva) >= self.pages.len() {
+            return Err(Error::NotMapped);
+        }
+
+        self.pages[page(rva)].ok_or(Error::NotMapped)
+    }
 }
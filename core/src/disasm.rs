@@ -0,0 +1,169 @@
+//! an architecture-agnostic decoding/classification interface, so that
+//! `Workspace::read_insn` and the CFG recovery built on top of it
+//! (`get_basic_blocks`) aren't permanently wired to x86. `ZydisDisassembler`
+//! remains the default backend; `WorkspaceBuilder::with_decoder` installs an
+//! alternate one (e.g. for a fixed-width register/immediate bytecode ISA)
+//! without requiring any change to the xref/basic-block logic built on top
+//! of `Disassembler`.
+
+use failure::{Error, Fail};
+
+use super::{
+    arch::{Arch, RVA, VA},
+    workspace::Workspace,
+    xref::XrefType,
+};
+
+#[derive(Debug, Fail)]
+pub enum DisasmError {
+    #[fail(display = "failed to decode instruction")]
+    InvalidInstruction,
+}
+
+/// an instruction decoded by a `Disassembler` backend.
+///
+/// `x86`, when present, carries the full zydis decode, for analyzers that
+/// need x86-specific operand detail beyond `length` (e.g. the PE
+/// jump-table and FLIRT-reference analyzers); other backends leave it
+/// `None`.
+#[derive(Debug, Clone)]
+pub struct DecodedInsn {
+    /// length of the instruction, in bytes.
+    pub length: u8,
+    pub x86:    Option<zydis::DecodedInstruction>,
+}
+
+/// decodes instructions for one instruction set, and classifies the
+/// control-flow edges leaving them, so that CFG recovery (`get_basic_blocks`
+/// and friends) doesn't need to know anything about the underlying ISA.
+pub trait Disassembler {
+    /// Decode a single instruction from the start of `buf`.
+    ///
+    /// Returns `Ok(None)` for a buffer that's well-formed but too short to
+    /// hold a full instruction; a genuinely invalid encoding is an `Err`.
+    fn decode(&self, buf: &[u8]) -> Result<Option<DecodedInsn>, Error>;
+
+    /// Length, in bytes, of the instruction at the start of `buf`.
+    ///
+    /// The default implementation just decodes and reads `.length`; a
+    /// backend may override this with something cheaper.
+    fn insn_length(&self, buf: &[u8]) -> Result<u8, Error> {
+        match self.decode(buf)? {
+            Some(insn) => Ok(insn.length),
+            None => Err(DisasmError::InvalidInstruction.into()),
+        }
+    }
+
+    /// Classify the control-flow edges leaving `insn`, which was decoded at
+    /// `rva`. An ordinary, non-branching instruction classifies as a
+    /// single `Fallthrough` edge to `rva + insn.length`; a `RET`-like
+    /// instruction classifies to no edges at all.
+    fn classify(&self, ws: &Workspace, rva: RVA, insn: &DecodedInsn) -> Vec<(XrefType, RVA)>;
+}
+
+/// the default `Disassembler`, backed by zydis, for x86/x86-64.
+///
+/// Example:
+///
+/// ```
+/// use lancelot::test;
+/// use lancelot::arch::{Arch, RVA};
+/// use lancelot::disasm::{Disassembler, ZydisDisassembler};
+/// use lancelot::xref::XrefType;
+///
+/// let ws = test::get_shellcode32_workspace(b"\xEB\xFE");
+/// let insn = ws.read_insn(RVA(0x0)).unwrap();
+///
+/// let disasm = ZydisDisassembler::new(Arch::X32);
+/// let edges = disasm.classify(&ws, RVA(0x0), &insn);
+///
+/// assert_eq!(edges.len(), 1);
+/// assert!(matches!(edges[0].0, XrefType::UnconditionalJump));
+/// assert_eq!(edges[0].1, RVA(0x0));
+/// ```
+pub struct ZydisDisassembler {
+    decoder: zydis::Decoder,
+}
+
+impl ZydisDisassembler {
+    pub fn new(arch: Arch) -> ZydisDisassembler {
+        let decoder = match arch {
+            Arch::X32 => zydis::Decoder::new(zydis::MachineMode::LEGACY_32, zydis::AddressWidth::_32).unwrap(),
+            Arch::X64 => zydis::Decoder::new(zydis::MachineMode::LONG_64, zydis::AddressWidth::_64).unwrap(),
+        };
+        ZydisDisassembler { decoder }
+    }
+
+    /// resolve a direct branch/call's single operand to the RVA it targets,
+    /// following the same `calc_absolute_address` convention already used
+    /// by the FLIRT reference resolver, rather than assuming the operand's
+    /// immediate is already an absolute address.
+    fn direct_target(ws: &Workspace, rva: RVA, insn: &zydis::DecodedInstruction) -> Option<RVA> {
+        let va = ws.va(rva)?;
+        let op = insn.operands.get(0)?;
+        let target = insn.calc_absolute_address(u64::from(va), op).ok()?;
+        ws.rva(VA::from(target))
+    }
+}
+
+impl Disassembler for ZydisDisassembler {
+    fn decode(&self, buf: &[u8]) -> Result<Option<DecodedInsn>, Error> {
+        match self.decoder.decode(buf) {
+            Ok(Some(insn)) => Ok(Some(DecodedInsn {
+                length: insn.length,
+                x86:    Some(insn),
+            })),
+            Ok(None) => Ok(None),
+            Err(_) => Err(DisasmError::InvalidInstruction.into()),
+        }
+    }
+
+    fn classify(&self, ws: &Workspace, rva: RVA, insn: &DecodedInsn) -> Vec<(XrefType, RVA)> {
+        let fallthrough = rva + insn.length as i64;
+
+        let x86 = match insn.x86.as_ref() {
+            Some(x86) => x86,
+            // a non-x86 decode reaching the x86 classifier can only mean a
+            // misconfigured workspace; treat it as a dead end rather than
+            // panicking.
+            None => return vec![],
+        };
+
+        match x86.mnemonic {
+            zydis::Mnemonic::JMP => Self::direct_target(ws, rva, x86)
+                .map(|target| vec![(XrefType::UnconditionalJump, target)])
+                .unwrap_or_default(),
+
+            zydis::Mnemonic::JZ
+            | zydis::Mnemonic::JNZ
+            | zydis::Mnemonic::JS
+            | zydis::Mnemonic::JNS
+            | zydis::Mnemonic::JB
+            | zydis::Mnemonic::JNB
+            | zydis::Mnemonic::JBE
+            | zydis::Mnemonic::JNBE
+            | zydis::Mnemonic::JL
+            | zydis::Mnemonic::JNL
+            | zydis::Mnemonic::JLE
+            | zydis::Mnemonic::JNLE => {
+                let mut edges = vec![(XrefType::Fallthrough, fallthrough)];
+                if let Some(target) = Self::direct_target(ws, rva, x86) {
+                    edges.push((XrefType::ConditionalJump, target));
+                }
+                edges
+            }
+
+            zydis::Mnemonic::CALL => {
+                let mut edges = vec![(XrefType::Fallthrough, fallthrough)];
+                if let Some(target) = Self::direct_target(ws, rva, x86) {
+                    edges.push((XrefType::Call, target));
+                }
+                edges
+            }
+
+            zydis::Mnemonic::RET => vec![],
+
+            _ => vec![(XrefType::Fallthrough, fallthrough)],
+        }
+    }
+}
@@ -0,0 +1,752 @@
+//! a small, soft-paged concrete emulator used to discover code that static
+//! analysis (entry points, relocations, xrefs) misses: indirect calls and
+//! jumps through registers/memory, jump tables, and self-modifying stubs.
+//!
+//! this is deliberately not a general-purpose x86 interpreter. it supports
+//! enough of the common data-movement and arithmetic instruction set to
+//! track register/memory values through a basic block; anything it doesn't
+//! recognize raises `Trap::UnsupportedInstruction` and cleanly ends the
+//! trace rather than guessing or panicking.
+//!
+//! there are two ways to run it. `Emulator`/`EmulationAnalyzer` emulate
+//! whole functions, writing through to the real workspace and registering
+//! anything they discover (needs a mutable workspace). `resolve_block_target`
+//! is a lighter, read-only sibling that speculatively traces a single basic
+//! block to resolve one indirect `call`/`jmp`, for use from contexts -- like
+//! `Workspace::get_basic_blocks` -- that only hold a shared borrow.
+
+use std::collections::HashMap;
+
+use failure::{Error, Fail};
+use log::{debug, trace, warn};
+use zydis::{Mnemonic, Register};
+
+use super::{
+    analysis::Analyzer,
+    arch::{RVA, VA},
+    loader::Permissions,
+    workspace::Workspace,
+};
+
+#[derive(Debug, Fail)]
+pub enum Trap {
+    #[fail(display = "fetch from a non-executable or unmapped address: {}", _0)]
+    InvalidFetch(RVA),
+    #[fail(display = "read from an unmapped address: {}", _0)]
+    UnmappedRead(RVA),
+    #[fail(display = "write to a non-writable or unmapped address: {}", _0)]
+    WriteProtected(RVA),
+    #[fail(display = "could not decode an instruction at: {}", _0)]
+    InvalidInstruction(RVA),
+    #[fail(display = "instruction is not emulated: {:?}", _0)]
+    UnsupportedInstruction(Mnemonic),
+    #[fail(display = "exceeded the instruction budget ({} instructions)", _0)]
+    BudgetExceeded(usize),
+}
+
+/// a flat bank of general-purpose registers. sub-register writes (e.g. to
+/// `EAX`) are tracked under their own key rather than merged into their
+/// parent (`RAX`): this is a known simplification, since doing this
+/// correctly requires per-architecture knowledge of register aliasing.
+#[derive(Default)]
+struct RegisterFile {
+    values: HashMap<Register, u64>,
+}
+
+impl RegisterFile {
+    fn get(&self, reg: Register) -> u64 {
+        *self.values.get(&reg).unwrap_or(&0)
+    }
+
+    fn set(&mut self, reg: Register, value: u64) {
+        self.values.insert(reg, value);
+    }
+
+    /// resolve the effective address of a memory operand: `[base + index*scale +
+    /// disp]`, using whatever values are currently tracked for `base`/`index`.
+    /// the result is an absolute address, matching how pointers are carried
+    /// in registers/memory throughout the emulator.
+    ///
+    /// `rip` is the VA of the instruction *after* the one this operand
+    /// belongs to: on x86-64, `RIP` as a base register doesn't name a
+    /// tracked value the way `RAX` does -- it's always the address of the
+    /// next instruction, so it's threaded in by the caller rather than
+    /// looked up in `values`.
+    fn effective_address(&self, op: &zydis::DecodedOperand, rip: u64) -> u64 {
+        let base = if op.mem.base == Register::RIP {
+            rip
+        } else if op.mem.base != Register::NONE {
+            self.get(op.mem.base)
+        } else {
+            0
+        };
+        let index = if op.mem.index != Register::NONE {
+            self.get(op.mem.index).wrapping_mul(op.mem.scale as u64)
+        } else {
+            0
+        };
+        let disp = op.mem.disp.displacement;
+
+        (base.wrapping_add(index) as i64).wrapping_add(disp) as u64
+    }
+}
+
+/// the condition-code flags that `Jcc`/`CMP`/`TEST` reason about.
+#[derive(Default, Clone, Copy)]
+struct Flags {
+    zf: bool,
+    sf: bool,
+    cf: bool,
+}
+
+impl Flags {
+    fn from_sub(lhs: u64, rhs: u64) -> Flags {
+        let (result, carried) = lhs.overflowing_sub(rhs);
+        Flags {
+            zf: result == 0,
+            sf: (result as i64) < 0,
+            cf: carried,
+        }
+    }
+}
+
+/// the outcome of emulating a single basic block's worth of instructions
+/// from a starting address.
+#[derive(Debug, Default)]
+pub struct EmulationReport {
+    /// addresses of instructions that were actually stepped over.
+    pub executed: Vec<RVA>,
+    /// control-flow targets resolved during the trace (call/jmp/jcc), in
+    /// the order they were discovered.
+    pub discovered: Vec<RVA>,
+}
+
+/// a soft-paged virtual machine that steps over decoded instructions,
+/// reading/writing through `Workspace`'s address space and raising a typed
+/// `Trap` rather than panicking when it runs off the rails.
+pub struct Emulator {
+    regs:    RegisterFile,
+    flags:   Flags,
+    /// maximum number of instructions to execute before giving up, so that
+    /// an emulated loop (or a mistake in the emulator itself) can't run
+    /// forever.
+    budget:  usize,
+}
+
+impl Emulator {
+    pub fn new(budget: usize) -> Emulator {
+        Emulator {
+            regs: RegisterFile::default(),
+            flags: Flags::default(),
+            budget,
+        }
+    }
+
+    fn section_perms(ws: &Workspace, rva: RVA) -> Permissions {
+        ws.module
+            .sections
+            .iter()
+            .find(|section| section.contains(rva))
+            .map(|section| section.perms)
+            .unwrap_or_else(Permissions::empty)
+    }
+
+    /// translate an absolute address (as carried in a register or computed
+    /// from a memory operand) into an `RVA`, raising the same trap whether
+    /// the failure is "not mapped" or "not even in the address space".
+    fn to_rva(ws: &Workspace, addr: u64) -> Result<RVA, Trap> {
+        ws.rva(VA::from(addr)).ok_or(Trap::UnmappedRead(RVA::from(0)))
+    }
+
+    fn mem_read(ws: &Workspace, addr: u64, size_bytes: usize) -> Result<u64, Trap> {
+        let rva = Self::to_rva(ws, addr)?;
+        if !Self::section_perms(ws, rva).intersects(Permissions::R) {
+            return Err(Trap::UnmappedRead(rva));
+        }
+
+        match size_bytes {
+            1 => ws.read_u8(rva).map(u64::from),
+            2 => ws.read_u16(rva).map(u64::from),
+            4 => ws.read_u32(rva).map(u64::from),
+            _ => ws.read_u64(rva),
+        }
+        .map_err(|_| Trap::UnmappedRead(rva))
+    }
+
+    fn mem_write(ws: &mut Workspace, addr: u64, value: u64, size_bytes: usize) -> Result<(), Trap> {
+        let rva = ws.rva(VA::from(addr)).ok_or(Trap::WriteProtected(RVA::from(0)))?;
+        if !Self::section_perms(ws, rva).intersects(Permissions::W) {
+            return Err(Trap::WriteProtected(rva));
+        }
+
+        let result = match size_bytes {
+            1 => ws.write_u8(rva, value as u8),
+            2 => ws.write_u16(rva, value as u16),
+            4 => ws.write_u32(rva, value as u32),
+            _ => ws.write_u64(rva, value),
+        };
+
+        result.map_err(|_| Trap::WriteProtected(rva))
+    }
+
+    /// `fallthrough` is the address immediately after the current
+    /// instruction, i.e. what `RIP` reads as when used as a base register --
+    /// see `RegisterFile::effective_address`.
+    fn mem_operand_address(&self, ws: &Workspace, fallthrough: RVA, op: &zydis::DecodedOperand) -> u64 {
+        let rip = ws.va(fallthrough).map(u64::from).unwrap_or(0);
+        self.regs.effective_address(op, rip)
+    }
+
+    fn read_operand(&self, ws: &Workspace, fallthrough: RVA, op: &zydis::DecodedOperand) -> Result<u64, Trap> {
+        use zydis::OperandType;
+
+        match op.ty {
+            OperandType::REGISTER => Ok(self.regs.get(op.reg)),
+            OperandType::IMMEDIATE if op.imm.is_relative => {
+                // a relative branch displacement counts from the next instruction.
+                let next = ws.va(fallthrough).map(u64::from).unwrap_or(0);
+                Ok(next.wrapping_add(op.imm.value as u64))
+            }
+            OperandType::IMMEDIATE => Ok(op.imm.value as u64),
+            OperandType::MEMORY => {
+                let addr = self.mem_operand_address(ws, fallthrough, op);
+                Self::mem_read(ws, addr, (op.size / 8) as usize)
+            }
+            _ => Err(Trap::UnsupportedInstruction(Mnemonic::INVALID)),
+        }
+    }
+
+    fn write_operand(
+        &mut self,
+        ws: &mut Workspace,
+        fallthrough: RVA,
+        op: &zydis::DecodedOperand,
+        value: u64,
+    ) -> Result<(), Trap> {
+        use zydis::OperandType;
+
+        match op.ty {
+            OperandType::REGISTER => {
+                self.regs.set(op.reg, value);
+                Ok(())
+            }
+            OperandType::MEMORY => {
+                let addr = self.mem_operand_address(ws, fallthrough, op);
+                Self::mem_write(ws, addr, value, (op.size / 8) as usize)
+            }
+            _ => Err(Trap::UnsupportedInstruction(Mnemonic::INVALID)),
+        }
+    }
+
+    /// evaluate whether a `Jcc` mnemonic's condition currently holds,
+    /// given the flags left behind by the last `CMP`/`TEST`/arithmetic op.
+    fn eval_condition(&self, mnemonic: Mnemonic) -> Option<bool> {
+        match mnemonic {
+            Mnemonic::JZ => Some(self.flags.zf),
+            Mnemonic::JNZ => Some(!self.flags.zf),
+            Mnemonic::JS => Some(self.flags.sf),
+            Mnemonic::JNS => Some(!self.flags.sf),
+            Mnemonic::JB => Some(self.flags.cf),
+            Mnemonic::JNB => Some(!self.flags.cf),
+            Mnemonic::JBE => Some(self.flags.cf || self.flags.zf),
+            Mnemonic::JNBE => Some(!self.flags.cf && !self.flags.zf),
+            Mnemonic::JL => Some(self.flags.sf != self.flags.cf), // approximation: ignores OF
+            Mnemonic::JNL => Some(self.flags.sf == self.flags.cf),
+            Mnemonic::JLE => Some(self.flags.zf || self.flags.sf != self.flags.cf),
+            Mnemonic::JNLE => Some(!self.flags.zf && self.flags.sf == self.flags.cf),
+            _ => None,
+        }
+    }
+
+    /// step over a single instruction at `ip`, updating registers/flags/memory
+    /// in place, and return the next instruction pointer to fetch: the
+    /// fallthrough, or the target of a taken jump. returns `None` when the
+    /// trace ends here (a `RET`, or a jump whose target isn't mapped).
+    /// control-flow instructions also record their resolved targets into
+    /// `report`.
+    fn step(
+        &mut self,
+        ws: &mut Workspace,
+        ip: RVA,
+        report: &mut EmulationReport,
+    ) -> Result<Option<RVA>, Trap> {
+        if !Self::section_perms(ws, ip).intersects(Permissions::X) {
+            return Err(Trap::InvalidFetch(ip));
+        }
+
+        let decoded = ws.read_insn(ip).map_err(|_| Trap::InvalidInstruction(ip))?;
+        let length = RVA::from(decoded.length as i64);
+        let fallthrough = ip + length;
+        // this emulator only understands x86; a non-x86 decode means the
+        // workspace was configured with an alternate `Disassembler`, which
+        // this analyzer doesn't support.
+        let insn = decoded.x86.ok_or(Trap::InvalidInstruction(ip))?;
+
+        // best-effort: a failure here doesn't invalidate the step we just
+        // decoded, it just means this byte range stays untagged.
+        let _ = ws.mark_insn_provenance(ip);
+
+        report.executed.push(ip);
+        trace!("emulator: {} {:?}", ip, insn.mnemonic);
+
+        match insn.mnemonic {
+            Mnemonic::NOP => {}
+
+            Mnemonic::MOV | Mnemonic::MOVZX | Mnemonic::MOVSXD => {
+                let value = self.read_operand(ws, fallthrough, &insn.operands[1])?;
+                self.write_operand(ws, fallthrough, &insn.operands[0], value)?;
+            }
+
+            Mnemonic::LEA => {
+                let addr = self.mem_operand_address(ws, fallthrough, &insn.operands[1]);
+                self.write_operand(ws, fallthrough, &insn.operands[0], addr)?;
+            }
+
+            Mnemonic::PUSH => {
+                let value = self.read_operand(ws, fallthrough, &insn.operands[0])?;
+                let rsp = self.regs.get(Register::RSP).wrapping_sub(8);
+                self.regs.set(Register::RSP, rsp);
+                Self::mem_write(ws, rsp, value, 8)?;
+            }
+
+            Mnemonic::POP => {
+                let rsp = self.regs.get(Register::RSP);
+                let value = Self::mem_read(ws, rsp, 8)?;
+                self.regs.set(Register::RSP, rsp.wrapping_add(8));
+                self.write_operand(ws, fallthrough, &insn.operands[0], value)?;
+            }
+
+            Mnemonic::ADD | Mnemonic::SUB | Mnemonic::AND | Mnemonic::OR | Mnemonic::XOR => {
+                let lhs = self.read_operand(ws, fallthrough, &insn.operands[0])?;
+                let rhs = self.read_operand(ws, fallthrough, &insn.operands[1])?;
+                let result = match insn.mnemonic {
+                    Mnemonic::ADD => lhs.wrapping_add(rhs),
+                    Mnemonic::SUB => lhs.wrapping_sub(rhs),
+                    Mnemonic::AND => lhs & rhs,
+                    Mnemonic::OR => lhs | rhs,
+                    Mnemonic::XOR => lhs ^ rhs,
+                    _ => unreachable!(),
+                };
+                self.flags = Flags::from_sub(result, 0);
+                self.write_operand(ws, fallthrough, &insn.operands[0], result)?;
+            }
+
+            Mnemonic::INC => {
+                let value = self.read_operand(ws, fallthrough, &insn.operands[0])?.wrapping_add(1);
+                self.write_operand(ws, fallthrough, &insn.operands[0], value)?;
+            }
+
+            Mnemonic::DEC => {
+                let value = self.read_operand(ws, fallthrough, &insn.operands[0])?.wrapping_sub(1);
+                self.write_operand(ws, fallthrough, &insn.operands[0], value)?;
+            }
+
+            Mnemonic::CMP => {
+                let lhs = self.read_operand(ws, fallthrough, &insn.operands[0])?;
+                let rhs = self.read_operand(ws, fallthrough, &insn.operands[1])?;
+                self.flags = Flags::from_sub(lhs, rhs);
+            }
+
+            Mnemonic::TEST => {
+                let lhs = self.read_operand(ws, fallthrough, &insn.operands[0])?;
+                let rhs = self.read_operand(ws, fallthrough, &insn.operands[1])?;
+                self.flags = Flags::from_sub(lhs & rhs, 0);
+            }
+
+            Mnemonic::JMP => {
+                let target_va = self.read_operand(ws, fallthrough, &insn.operands[0])?;
+                if let Some(target) = ws.rva(VA::from(target_va)) {
+                    debug!("emulator: {} jmp -> {}", ip, target);
+                    report.discovered.push(target);
+                    return Ok(Some(target));
+                }
+                return Ok(None);
+            }
+
+            Mnemonic::JZ
+            | Mnemonic::JNZ
+            | Mnemonic::JS
+            | Mnemonic::JNS
+            | Mnemonic::JB
+            | Mnemonic::JNB
+            | Mnemonic::JBE
+            | Mnemonic::JNBE
+            | Mnemonic::JL
+            | Mnemonic::JNL
+            | Mnemonic::JLE
+            | Mnemonic::JNLE => {
+                let target_va = self.read_operand(ws, fallthrough, &insn.operands[0])?;
+                if let Some(target) = ws.rva(VA::from(target_va)) {
+                    debug!("emulator: {} {:?} -> {}", ip, insn.mnemonic, target);
+                    report.discovered.push(target);
+                }
+
+                // both arms of a conditional branch are plausible future
+                // code: take whichever the current flags resolve to, and
+                // let the caller re-emulate the other from the recorded
+                // discovery if it's ever reached another way.
+                match self.eval_condition(insn.mnemonic) {
+                    Some(true) => {
+                        if let Some(target) = ws.rva(VA::from(target_va)) {
+                            return Ok(Some(target));
+                        }
+                    }
+                    _ => return Ok(Some(fallthrough)),
+                }
+            }
+
+            Mnemonic::CALL => {
+                let target_va = self.read_operand(ws, fallthrough, &insn.operands[0])?;
+                if let Some(target) = ws.rva(VA::from(target_va)) {
+                    debug!("emulator: {} call -> {}", ip, target);
+                    report.discovered.push(target);
+                }
+                // we don't recurse into the callee: the return address is
+                // wherever execution continues, i.e. the fallthrough.
+                let rsp = self.regs.get(Register::RSP).wrapping_sub(8);
+                self.regs.set(Register::RSP, rsp);
+                let return_va: u64 = ws.va(fallthrough).map(u64::from).unwrap_or(0);
+                Self::mem_write(ws, rsp, return_va, 8)?;
+            }
+
+            Mnemonic::RET => {
+                // end of the basic block (and, usually, the function):
+                // nothing further to discover along this path.
+                return Ok(None);
+            }
+
+            other => return Err(Trap::UnsupportedInstruction(other)),
+        }
+
+        Ok(Some(fallthrough))
+    }
+
+    /// emulate forward from `start` until a trap, an unresolvable branch, or
+    /// the instruction budget is exhausted. every resolved control-flow
+    /// target is recorded in the workspace as a function/instruction start,
+    /// so a subsequent `ws.analyze()` can pick up the newly discovered code.
+    pub fn run(&mut self, ws: &mut Workspace, start: RVA) -> Result<EmulationReport, Error> {
+        let mut report = EmulationReport::default();
+        let mut ip = start;
+
+        loop {
+            if report.executed.len() >= self.budget {
+                warn!("emulator: budget exceeded starting from {}", start);
+                return Err(Trap::BudgetExceeded(self.budget).into());
+            }
+
+            match self.step(ws, ip, &mut report) {
+                Ok(Some(next)) => ip = next,
+                Ok(None) => break,
+                Err(trap) => {
+                    debug!("emulator: trace from {} stopped: {}", start, trap);
+                    break;
+                }
+            }
+        }
+
+        for &target in report.discovered.iter() {
+            ws.make_function(target)?;
+        }
+        if !report.discovered.is_empty() {
+            ws.analyze()?;
+        }
+
+        Ok(report)
+    }
+}
+
+/// how far a `BlockEmulator` trace will step before giving up, so a
+/// malformed or adversarial block can't spin forever. basic blocks are
+/// short by construction, so this is generous headroom rather than a tight
+/// limit.
+const MAX_BLOCK_TRACE_INSNS: usize = 64;
+
+/// the outcome of resolving a single basic block's indirect jump target via
+/// `resolve_block_target`: the concrete destination, plus any memory
+/// locations the trace wrote to along the way (e.g. a spilled register that
+/// later feeds the jump), reported back as RVA/value pairs rather than raw
+/// addresses.
+#[derive(Debug)]
+pub struct BlockEmulationResult {
+    pub target: RVA,
+    pub writes: Vec<(RVA, u64)>,
+}
+
+/// a read-only sibling of `Emulator`, used to speculatively resolve the
+/// target of a computed (register-indirect) jump from within
+/// `Workspace::get_basic_blocks`, which only holds a shared borrow of the
+/// workspace and so can't use `Emulator` (which writes through to real
+/// memory and records discovered functions). writes observed during the
+/// trace are kept in a local scratch overlay instead of touching the
+/// workspace, and nothing here calls `ws.make_function`/`ws.analyze` --
+/// that remains the job of the full `EmulationAnalyzer` pass.
+struct BlockEmulator {
+    regs:    RegisterFile,
+    scratch: HashMap<u64, (u64, usize)>,
+}
+
+enum StepOutcome {
+    Continue(RVA),
+    Resolved(RVA),
+    Stopped,
+}
+
+impl BlockEmulator {
+    fn new() -> BlockEmulator {
+        BlockEmulator {
+            regs:    RegisterFile::default(),
+            scratch: HashMap::new(),
+        }
+    }
+
+    fn mem_read(&self, ws: &Workspace, addr: u64, size_bytes: usize) -> Option<u64> {
+        if let Some(&(value, written_size)) = self.scratch.get(&addr) {
+            if written_size == size_bytes {
+                return Some(value);
+            }
+        }
+
+        let rva = ws.rva(VA::from(addr))?;
+        if !Emulator::section_perms(ws, rva).intersects(Permissions::R) {
+            return None;
+        }
+
+        match size_bytes {
+            1 => ws.read_u8(rva).map(u64::from),
+            2 => ws.read_u16(rva).map(u64::from),
+            4 => ws.read_u32(rva).map(u64::from),
+            _ => ws.read_u64(rva),
+        }
+        .ok()
+    }
+
+    fn read_operand(&self, ws: &Workspace, rip: u64, op: &zydis::DecodedOperand) -> Option<u64> {
+        use zydis::OperandType;
+
+        match op.ty {
+            OperandType::REGISTER => Some(self.regs.get(op.reg)),
+            OperandType::IMMEDIATE => Some(op.imm.value as u64),
+            OperandType::MEMORY => {
+                let addr = self.regs.effective_address(op, rip);
+                self.mem_read(ws, addr, (op.size / 8) as usize)
+            }
+            _ => None,
+        }
+    }
+
+    fn write_operand(&mut self, rip: u64, op: &zydis::DecodedOperand, value: u64) -> Option<()> {
+        use zydis::OperandType;
+
+        match op.ty {
+            OperandType::REGISTER => {
+                self.regs.set(op.reg, value);
+                Some(())
+            }
+            OperandType::MEMORY => {
+                let addr = self.regs.effective_address(op, rip);
+                self.scratch.insert(addr, (value, (op.size / 8) as usize));
+                Some(())
+            }
+            _ => None,
+        }
+    }
+
+    /// step over a single instruction, matching the data-movement/arithmetic
+    /// subset that `Emulator::step` handles; anything else (including a
+    /// direct/conditional branch, which ordinary xref recovery already
+    /// covers) stops the trace rather than guessing.
+    fn step(&mut self, ws: &Workspace, ip: RVA, length: RVA) -> StepOutcome {
+        let insn = match ws.read_insn(ip).ok().and_then(|insn| insn.x86) {
+            Some(insn) => insn,
+            None => return StepOutcome::Stopped,
+        };
+        let fallthrough = ip + length;
+        let rip = ws.va(fallthrough).map(u64::from).unwrap_or(0);
+
+        match insn.mnemonic {
+            Mnemonic::NOP => {}
+
+            Mnemonic::MOV | Mnemonic::MOVZX | Mnemonic::MOVSXD => {
+                let value = match self.read_operand(ws, rip, &insn.operands[1]) {
+                    Some(value) => value,
+                    None => return StepOutcome::Stopped,
+                };
+                if self.write_operand(rip, &insn.operands[0], value).is_none() {
+                    return StepOutcome::Stopped;
+                }
+            }
+
+            Mnemonic::LEA => {
+                let addr = self.regs.effective_address(&insn.operands[1], rip);
+                if self.write_operand(rip, &insn.operands[0], addr).is_none() {
+                    return StepOutcome::Stopped;
+                }
+            }
+
+            Mnemonic::PUSH => {
+                let value = match self.read_operand(ws, rip, &insn.operands[0]) {
+                    Some(value) => value,
+                    None => return StepOutcome::Stopped,
+                };
+                let rsp = self.regs.get(Register::RSP).wrapping_sub(8);
+                self.regs.set(Register::RSP, rsp);
+                self.scratch.insert(rsp, (value, 8));
+            }
+
+            Mnemonic::POP => {
+                let rsp = self.regs.get(Register::RSP);
+                let value = match self.mem_read(ws, rsp, 8) {
+                    Some(value) => value,
+                    None => return StepOutcome::Stopped,
+                };
+                self.regs.set(Register::RSP, rsp.wrapping_add(8));
+                if self.write_operand(rip, &insn.operands[0], value).is_none() {
+                    return StepOutcome::Stopped;
+                }
+            }
+
+            Mnemonic::ADD | Mnemonic::SUB | Mnemonic::AND | Mnemonic::OR | Mnemonic::XOR => {
+                let lhs = match self.read_operand(ws, rip, &insn.operands[0]) {
+                    Some(value) => value,
+                    None => return StepOutcome::Stopped,
+                };
+                let rhs = match self.read_operand(ws, rip, &insn.operands[1]) {
+                    Some(value) => value,
+                    None => return StepOutcome::Stopped,
+                };
+                let result = match insn.mnemonic {
+                    Mnemonic::ADD => lhs.wrapping_add(rhs),
+                    Mnemonic::SUB => lhs.wrapping_sub(rhs),
+                    Mnemonic::AND => lhs & rhs,
+                    Mnemonic::OR => lhs | rhs,
+                    Mnemonic::XOR => lhs ^ rhs,
+                    _ => unreachable!(),
+                };
+                if self.write_operand(rip, &insn.operands[0], result).is_none() {
+                    return StepOutcome::Stopped;
+                }
+            }
+
+            Mnemonic::JMP | Mnemonic::CALL => {
+                // a direct branch has nothing to resolve.
+                if insn.operands[0].ty == zydis::OperandType::IMMEDIATE {
+                    return StepOutcome::Stopped;
+                }
+
+                // the terminating instruction of the trace: resolve its
+                // target from the current (speculative) register/memory
+                // state rather than stepping past it.
+                return match self.read_operand(ws, rip, &insn.operands[0]) {
+                    Some(target_va) => match ws.rva(VA::from(target_va)) {
+                        Some(target) => StepOutcome::Resolved(target),
+                        None => StepOutcome::Stopped,
+                    },
+                    None => StepOutcome::Stopped,
+                };
+            }
+
+            _ => return StepOutcome::Stopped,
+        }
+
+        StepOutcome::Continue(fallthrough)
+    }
+}
+
+/// speculatively emulate a basic block's instructions (in isolation, with a
+/// zeroed initial register state) to resolve the concrete destination of a
+/// terminating indirect `call`/`jmp` that static xref recovery left
+/// unresolved -- e.g. `jmp rax` or `call qword [rip + 0x2000]`.
+///
+/// this is read-only: unlike `Emulator::run`, it never writes through to the
+/// workspace and never registers discovered code itself, so it's safe to
+/// call from contexts (like `Workspace::get_basic_blocks`) that only hold a
+/// shared borrow of the workspace. the caller decides what to do with the
+/// resolved target (e.g. add it as a CFG successor, or queue it for a later
+/// `ws.make_function`).
+///
+/// ```
+/// use lancelot::test;
+/// use lancelot::arch::RVA;
+/// use lancelot::emulator::resolve_block_target;
+///
+/// // call qword [rip+0x2] ; the call's RIP is the address right after the
+/// // 6-byte instruction (0x6), so the pointer lives at 0x6 + 0x2 == 0x8.
+/// let ws = test::get_shellcode64_workspace(
+///     b"\xFF\x15\x02\x00\x00\x00\x90\x90\x00\x00\x00\x00\x00\x00\x00\x00",
+/// );
+///
+/// let result = resolve_block_target(&ws, &[RVA(0x0)]).unwrap();
+/// assert_eq!(result.target, RVA(0x0));
+/// ```
+pub fn resolve_block_target(ws: &Workspace, insns: &[RVA]) -> Option<BlockEmulationResult> {
+    let mut emu = BlockEmulator::new();
+
+    for (steps, &addr) in insns.iter().enumerate().take(MAX_BLOCK_TRACE_INSNS) {
+        let length = RVA::from(ws.get_insn_length(addr).ok()? as i64);
+        let is_last = steps == insns.len() - 1;
+
+        match emu.step(ws, addr, length) {
+            StepOutcome::Resolved(target) => {
+                let writes = emu
+                    .scratch
+                    .iter()
+                    .filter_map(|(&addr, &(value, _))| ws.rva(VA::from(addr)).map(|rva| (rva, value)))
+                    .collect();
+                return Some(BlockEmulationResult { target, writes });
+            }
+            StepOutcome::Continue(_) if is_last => {
+                // ran off the end of the block without hitting the branch we
+                // were asked to resolve (e.g. the caller passed a block that
+                // doesn't end in an indirect call/jmp).
+                return None;
+            }
+            StepOutcome::Continue(_) => continue,
+            StepOutcome::Stopped => return None,
+        }
+    }
+
+    None
+}
+
+/// a dynamic analyzer that complements the purely static
+/// `EntryPointAnalyzer`/`RelocAnalyzer`: it emulates from every function
+/// known to the workspace when it runs, recording any indirect call/jump
+/// targets it can resolve along the way. relocation targets that were not
+/// made into functions are not used as starting points.
+pub struct EmulationAnalyzer {
+    budget: usize,
+}
+
+impl EmulationAnalyzer {
+    pub fn new(budget: usize) -> EmulationAnalyzer {
+        EmulationAnalyzer { budget }
+    }
+}
+
+impl Default for EmulationAnalyzer {
+    fn default() -> EmulationAnalyzer {
+        // generous enough to cover most functions without a dedicated loop,
+        // conservative enough that a runaway trace can't stall analysis.
+        EmulationAnalyzer::new(0x1000)
+    }
+}
+
+impl Analyzer for EmulationAnalyzer {
+    fn get_name(&self) -> String {
+        "emulation-driven code discovery analyzer".to_string()
+    }
+
+    fn analyze(&self, ws: &mut Workspace) -> Result<(), Error> {
+        let starts: Vec<RVA> = ws.get_functions().cloned().collect();
+
+        for start in starts {
+            let mut emu = Emulator::new(self.budget);
+            if let Err(e) = emu.run(ws, start) {
+                debug!("emulator: failed to emulate from {}: {}", start, e);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -7,8 +7,9 @@ use super::{
     analysis::Analyzer,
     arch::{Arch, RVA, VA},
     config::Config,
-    loaders::{pe::PELoader, sc::ShellcodeLoader},
+    loaders::{coff::CoffLoader, elf::ElfLoader, macho::MachOLoader, pe::PELoader, sc::ShellcodeLoader},
     pagemap::PageMap,
+    provenance::ProvenanceMap,
 };
 
 #[derive(Debug, Fail)]
@@ -23,11 +24,16 @@ pub enum LoaderError {
 pub enum FileFormat {
     Raw, // shellcode
     PE,
+    Elf,
+    MachO,
+    Coff, // relocatable object file, e.g. extracted from a .lib/.a archive
 }
 
 #[derive(Display, Clone, Copy)]
 pub enum Platform {
     Windows,
+    Linux,
+    MacOS,
 }
 
 bitflags! {
@@ -77,6 +83,11 @@ pub struct LoadedModule {
     pub base_address:  VA,
     pub sections:      Vec<Section>,
     pub address_space: PageMap<u8>,
+    /// per-byte provenance (uninitialized/data/pointer/instruction-body),
+    /// kept page-aligned with `address_space`. populated by the loader as
+    /// it maps segments, and refined by later analysis (the relocation
+    /// engine, the disassembler) as it learns more about each byte.
+    pub provenance:    ProvenanceMap,
 }
 
 impl LoadedModule {
@@ -119,6 +130,12 @@ pub fn default_loaders() -> Vec<Box<dyn Loader>> {
 
     loaders.push(Box::new(PELoader::new(Arch::X32)));
     loaders.push(Box::new(PELoader::new(Arch::X64)));
+    loaders.push(Box::new(MachOLoader::new(Arch::X32)));
+    loaders.push(Box::new(MachOLoader::new(Arch::X64)));
+    loaders.push(Box::new(ElfLoader::new(Arch::X32)));
+    loaders.push(Box::new(ElfLoader::new(Arch::X64)));
+    loaders.push(Box::new(CoffLoader::new(Arch::X32)));
+    loaders.push(Box::new(CoffLoader::new(Arch::X64)));
     loaders.push(Box::new(ShellcodeLoader::new(Platform::Windows, Arch::X32)));
     loaders.push(Box::new(ShellcodeLoader::new(Platform::Windows, Arch::X64)));
 
@@ -0,0 +1,62 @@
+use failure::Error;
+use goblin::archive::Archive;
+use log::{debug, info};
+
+use super::super::{config::Config, workspace::Workspace};
+
+/// does `buf` start with the `!<arch>\n` magic of a thin/fat static archive
+/// (`.lib`/`.a`)? such an archive isn't itself a loadable image: it's a
+/// collection of independent COFF/ELF object file members, each of which
+/// should be loaded (and analyzed) on its own.
+///
+/// unlike the `Loader` trait, `load` below doesn't produce a single
+/// `LoadedModule` -- there's no one base address or address space that
+/// could sensibly hold every member at once -- so it returns one `Workspace`
+/// per member instead, for the caller to inspect or analyze independently.
+pub fn taste(buf: &[u8]) -> bool {
+    buf.len() > 8 && &buf[0..8] == b"!<arch>\n"
+}
+
+/// load every object file member out of a `.lib`/`.a` archive.
+///
+/// a member that no registered loader recognizes (for example, an archive
+/// import/linker-directive member rather than a real object file) is
+/// skipped rather than failing the whole archive.
+///
+/// Example:
+///
+/// ```no_run
+/// use lancelot::config::Config;
+/// use lancelot::loaders::archive;
+///
+/// let buf = std::fs::read("foo.lib").unwrap();
+/// for ws in archive::load(&Config::default(), &buf).unwrap() {
+///   println!("{}: {} sections", ws.filename, ws.module.sections.len());
+/// }
+/// ```
+pub fn load(config: &Config, buf: &[u8]) -> Result<Vec<Workspace>, Error> {
+    let archive = Archive::parse(buf).map_err(|e| failure::err_msg(format!("failed to parse archive: {}", e)))?;
+
+    let mut workspaces = vec![];
+    for member in archive.members() {
+        let data = match archive.extract(member, buf) {
+            Ok(data) => data,
+            Err(e) => {
+                debug!("archive: failed to extract member {}: {}", member, e);
+                continue;
+            }
+        };
+
+        match Workspace::from_bytes(member, data).with_config(config.clone()).load() {
+            Ok(ws) => {
+                info!("archive: loaded member {} via {}", member, ws.loader.get_name());
+                workspaces.push(ws);
+            }
+            Err(e) => {
+                debug!("archive: member {} is not a loadable object: {}", member, e);
+            }
+        }
+    }
+
+    Ok(workspaces)
+}
@@ -0,0 +1,165 @@
+use failure::Error;
+use goblin::pe::{self, Coff};
+use log::debug;
+
+use super::super::{
+    analysis::{coff::CoffSymbolAnalyzer, Analyzer},
+    arch::{Arch, RVA, VA},
+    config::Config,
+    loader::{FileFormat, LoadedModule, Loader, Permissions, Platform, Section},
+    pagemap::PageMap,
+    provenance::{Provenance, ProvenanceMap},
+    strings::StringAnalyzer,
+};
+
+/// loads relocatable COFF object files (`.obj`), including those extracted
+/// from a `.lib`/`.a` static archive.
+///
+/// unlike a linked PE, a COFF object has no preferred base address and its
+/// relocations are unresolved against any particular load address, so its
+/// sections are mapped consecutively starting at RVA 0 rather than at the
+/// virtual addresses recorded in the file (there aren't any).
+pub struct CoffLoader {
+    arch: Arch,
+}
+
+impl CoffLoader {
+    pub fn new(arch: Arch) -> CoffLoader {
+        CoffLoader { arch }
+    }
+
+    fn machine(&self) -> u16 {
+        match self.arch {
+            Arch::X32 => pe::header::COFF_MACHINE_X86,
+            Arch::X64 => pe::header::COFF_MACHINE_X86_64,
+        }
+    }
+}
+
+/// translate a COFF section's `characteristics` (`IMAGE_SCN_MEM_READ`/
+/// `IMAGE_SCN_MEM_WRITE`/`IMAGE_SCN_MEM_EXECUTE`) into our `Permissions`
+/// bitflags.
+fn perms_from_characteristics(characteristics: u32) -> Permissions {
+    let mut perms = Permissions::empty();
+    if characteristics & pe::section_table::IMAGE_SCN_MEM_READ != 0 {
+        perms |= Permissions::R;
+    }
+    if characteristics & pe::section_table::IMAGE_SCN_MEM_WRITE != 0 {
+        perms |= Permissions::W;
+    }
+    if characteristics & pe::section_table::IMAGE_SCN_MEM_EXECUTE != 0 {
+        perms |= Permissions::X;
+    }
+    perms
+}
+
+/// `PageMap`/`DenseAddressSpace` only maps whole pages: `map`/`map_empty`
+/// require a page-aligned RVA and a size that's an exact multiple of
+/// `PAGE_SIZE` (anything else either panics on the alignment check or
+/// silently drops a trailing partial page via `chunks_exact`). sections
+/// must therefore each start on a page boundary and be mapped at their
+/// page-rounded size, not merely 16-byte-packed -- 16-byte packing still
+/// lets two sections share a page.
+const PAGE_SIZE: usize = 0x1000;
+
+/// round `size` up to the next page boundary.
+fn page_align(size: usize) -> usize {
+    (size + (PAGE_SIZE - 1)) & !(PAGE_SIZE - 1)
+}
+
+impl Loader for CoffLoader {
+    fn get_arch(&self) -> Arch {
+        self.arch
+    }
+
+    fn get_plat(&self) -> Platform {
+        Platform::Windows
+    }
+
+    fn get_file_format(&self) -> FileFormat {
+        FileFormat::Coff
+    }
+
+    fn taste(&self, _config: &Config, buf: &[u8]) -> bool {
+        // a COFF object has no magic number: it starts directly with an
+        // `IMAGE_FILE_HEADER`, so we sanity-check the machine field and
+        // lean on goblin to reject anything that isn't actually COFF.
+        if buf.len() < 20 {
+            return false;
+        }
+
+        let machine = u16::from_le_bytes([buf[0], buf[1]]);
+        if machine != self.machine() {
+            return false;
+        }
+
+        Coff::parse(buf).is_ok()
+    }
+
+    fn load(&self, _config: &Config, buf: &[u8]) -> Result<(LoadedModule, Vec<Box<dyn Analyzer>>), Error> {
+        let coff = Coff::parse(buf).map_err(|e| failure::err_msg(format!("failed to parse COFF object: {}", e)))?;
+
+        let total_size: usize = coff
+            .sections
+            .iter()
+            .map(|section| page_align(section.size_of_raw_data as usize))
+            .sum();
+
+        let mut address_space = PageMap::with_capacity(RVA::from(total_size as i64));
+        let mut provenance = ProvenanceMap::with_capacity(RVA::from(total_size as i64));
+
+        let mut sections = vec![];
+        let mut rva = RVA::from(0);
+        for section in coff.sections.iter() {
+            let name = section
+                .name()
+                .map(|name| name.to_string())
+                .unwrap_or_else(|_| "<unknown>".to_string());
+            let size = section.size_of_raw_data as usize;
+            let mapped_size = page_align(size);
+
+            // map the whole page-rounded region in one call: mapping `size`
+            // bytes directly (even against a page-aligned `rva`) would drop
+            // the trailing partial page via `chunks_exact`, so pad out to a
+            // full page of zeros first and overlay the real bytes on top.
+            let mut mapped = vec![0u8; mapped_size];
+            if section.pointer_to_raw_data != 0 && size > 0 {
+                let start = section.pointer_to_raw_data as usize;
+                mapped[..size].copy_from_slice(&buf[start..start + size]);
+                provenance.mark_range(rva, rva + size as i64, Provenance::Data)?;
+            }
+            // a section with no raw data backing it (e.g. `.bss`) stays
+            // `Uninitialized`: its bytes are zero-fill, not file content.
+            address_space.map(rva, &mapped)?;
+
+            debug!(
+                "COFF: mapping section {} at {} (size: {:#x}, perms: {:?})",
+                name,
+                rva,
+                size,
+                perms_from_characteristics(section.characteristics)
+            );
+
+            sections.push(Section {
+                addr: rva,
+                size: size as u32,
+                perms: perms_from_characteristics(section.characteristics),
+                name,
+            });
+
+            rva = rva + RVA::from(mapped_size as i64);
+        }
+
+        let module = LoadedModule {
+            base_address: VA::from(0u64),
+            sections,
+            address_space,
+            provenance,
+        };
+
+        let analyzers: Vec<Box<dyn Analyzer>> =
+            vec![Box::new(CoffSymbolAnalyzer::new()), Box::new(StringAnalyzer::new())];
+
+        Ok((module, analyzers))
+    }
+}
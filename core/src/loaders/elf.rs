@@ -0,0 +1,173 @@
+use failure::Error;
+use goblin::{
+    elf::{program_header::PT_LOAD, Elf},
+    Object,
+};
+use log::debug;
+
+use super::super::{
+    analysis::{elf::EntryPointAnalyzer, Analyzer},
+    arch::{Arch, RVA, VA},
+    config::Config,
+    emulator::EmulationAnalyzer,
+    loader::{FileFormat, LoadedModule, Loader, Permissions, Platform, Section},
+    pagemap::PageMap,
+    provenance::{Provenance, ProvenanceMap},
+    strings::StringAnalyzer,
+};
+
+/// loads little-endian Linux ELF executables and shared objects.
+pub struct ElfLoader {
+    arch: Arch,
+}
+
+impl ElfLoader {
+    pub fn new(arch: Arch) -> ElfLoader {
+        ElfLoader { arch }
+    }
+}
+
+/// `PageMap`/`DenseAddressSpace` only maps whole pages: `map`/`map_empty`
+/// require a size that's an exact multiple of `PAGE_SIZE` (anything else
+/// silently drops a trailing partial page via `chunks_exact`). every
+/// `PT_LOAD` segment's `memsz`/`filesz` must therefore be rounded up to a
+/// full page before mapping, or the segment's tail (including any `.bss`
+/// beyond `filesz`) never actually gets mapped.
+const PAGE_SIZE: usize = 0x1000;
+
+/// round `size` up to the next page boundary.
+fn page_align(size: usize) -> usize {
+    (size + (PAGE_SIZE - 1)) & !(PAGE_SIZE - 1)
+}
+
+/// translate the `PT_LOAD` segment's `p_flags` into our `Permissions`
+/// bitflags. ELF permission bits (`PF_X = 0x1`, `PF_W = 0x2`, `PF_R = 0x4`)
+/// don't line up with ours, so they're translated bit-by-bit rather than
+/// reinterpreted directly.
+fn perms_from_flags(p_flags: u32) -> Permissions {
+    let mut perms = Permissions::empty();
+    if p_flags & goblin::elf::program_header::PF_R != 0 {
+        perms |= Permissions::R;
+    }
+    if p_flags & goblin::elf::program_header::PF_W != 0 {
+        perms |= Permissions::W;
+    }
+    if p_flags & goblin::elf::program_header::PF_X != 0 {
+        perms |= Permissions::X;
+    }
+    perms
+}
+
+impl Loader for ElfLoader {
+    fn get_arch(&self) -> Arch {
+        self.arch
+    }
+
+    fn get_plat(&self) -> Platform {
+        Platform::Linux
+    }
+
+    fn get_file_format(&self) -> FileFormat {
+        FileFormat::Elf
+    }
+
+    fn taste(&self, _config: &Config, buf: &[u8]) -> bool {
+        if buf.len() < 0x14 {
+            return false;
+        }
+
+        if &buf[0..4] != b"\x7FELF" {
+            return false;
+        }
+
+        let elf = match Elf::parse(buf) {
+            Ok(elf) => elf,
+            Err(_) => return false,
+        };
+
+        match self.arch {
+            Arch::X32 => !elf.is_64,
+            Arch::X64 => elf.is_64,
+        }
+    }
+
+    fn load(&self, _config: &Config, buf: &[u8]) -> Result<(LoadedModule, Vec<Box<dyn Analyzer>>), Error> {
+        let elf = match Object::parse(buf) {
+            Ok(Object::Elf(elf)) => elf,
+            _ => panic!("can't analyze unexpected format"),
+        };
+
+        let segments: Vec<_> = elf
+            .program_headers
+            .iter()
+            .filter(|phdr| phdr.p_type == PT_LOAD)
+            .collect();
+
+        let base_address = segments
+            .iter()
+            .map(|phdr| phdr.p_vaddr)
+            .min()
+            .ok_or_else(|| failure::err_msg("ELF file has no PT_LOAD segments"))?;
+
+        let max_address = segments
+            .iter()
+            .map(|phdr| phdr.p_vaddr + page_align(phdr.p_memsz as usize) as u64)
+            .max()
+            .unwrap(); // danger: at least one segment, checked above.
+
+        let mut address_space = PageMap::with_capacity(RVA::from((max_address - base_address) as i64));
+        let mut provenance = ProvenanceMap::with_capacity(RVA::from((max_address - base_address) as i64));
+
+        let mut sections = vec![];
+        for (i, phdr) in segments.iter().enumerate() {
+            let rva = RVA::from((phdr.p_vaddr - base_address) as i64);
+            let filesz = phdr.p_filesz as usize;
+            let memsz = phdr.p_memsz as usize;
+            let offset = phdr.p_offset as usize;
+            let mapped_size = page_align(memsz);
+
+            // map the whole page-rounded region in one call: mapping `memsz`
+            // bytes directly would drop the trailing partial page via
+            // `chunks_exact`, so pad out to a full page of zeros first and
+            // overlay the real file bytes on top.
+            let mut mapped = vec![0u8; mapped_size];
+            if filesz > 0 {
+                mapped[..filesz].copy_from_slice(&buf[offset..offset + filesz]);
+                // the remainder of the segment, if any, is `.bss`-style
+                // zero-fill with no file backing: leave it `Uninitialized`.
+                provenance.mark_range(rva, rva + filesz as i64, Provenance::Data)?;
+            }
+            address_space.map(rva, &mapped)?;
+
+            debug!(
+                "ELF: mapping PT_LOAD segment {} at {} (size: {:#x}, perms: {:?})",
+                i,
+                rva,
+                memsz,
+                perms_from_flags(phdr.p_flags)
+            );
+
+            sections.push(Section {
+                addr: rva,
+                size: memsz as u32,
+                perms: perms_from_flags(phdr.p_flags),
+                name: format!("PT_LOAD[{}]", i),
+            });
+        }
+
+        let module = LoadedModule {
+            base_address: VA::from(base_address),
+            sections,
+            address_space,
+            provenance,
+        };
+
+        let analyzers: Vec<Box<dyn Analyzer>> = vec![
+            Box::new(EntryPointAnalyzer::new()),
+            Box::new(EmulationAnalyzer::default()),
+            Box::new(StringAnalyzer::new()),
+        ];
+
+        Ok((module, analyzers))
+    }
+}
@@ -0,0 +1,204 @@
+use failure::Error;
+use goblin::{
+    mach::{constants::cputype, segment::Segment, Mach, MachO},
+    Object,
+};
+use log::debug;
+
+use super::super::{
+    analysis::{macho::EntryPointAnalyzer, Analyzer},
+    arch::{Arch, RVA, VA},
+    config::Config,
+    emulator::EmulationAnalyzer,
+    loader::{FileFormat, LoadedModule, Loader, Permissions, Platform, Section},
+    pagemap::PageMap,
+    provenance::{Provenance, ProvenanceMap},
+    strings::StringAnalyzer,
+};
+
+/// loads Mach-O executables, including selecting the matching slice out of
+/// a fat/universal binary.
+pub struct MachOLoader {
+    arch: Arch,
+}
+
+impl MachOLoader {
+    pub fn new(arch: Arch) -> MachOLoader {
+        MachOLoader { arch }
+    }
+
+    fn cputype(&self) -> u32 {
+        match self.arch {
+            Arch::X32 => cputype::CPU_TYPE_X86,
+            Arch::X64 => cputype::CPU_TYPE_X86_64,
+        }
+    }
+
+    /// pick the single `MachO` out of `buf` that matches this loader's
+    /// architecture, whether `buf` is a plain Mach-O or a fat/universal
+    /// binary bundling multiple architectures.
+    fn select_macho<'a>(&self, buf: &'a [u8]) -> Option<MachO<'a>> {
+        match Mach::parse(buf).ok()? {
+            Mach::Binary(macho) => {
+                if macho.header.cputype == self.cputype() {
+                    Some(macho)
+                } else {
+                    None
+                }
+            }
+            Mach::Fat(fat) => fat
+                .iter_arches()
+                .enumerate()
+                .find(|(_, arch)| matches!(arch, Ok(arch) if arch.cputype == self.cputype()))
+                .and_then(|(i, _)| fat.get(i).ok()),
+        }
+    }
+}
+
+/// `PageMap`/`DenseAddressSpace` only maps whole pages: `map`/`map_empty`
+/// require a size that's an exact multiple of `PAGE_SIZE` (anything else
+/// silently drops a trailing partial page via `chunks_exact`). every
+/// segment's `vmsize`/`filesize` must therefore be rounded up to a full
+/// page before mapping, or the segment's tail (e.g. `__bss`) never
+/// actually gets mapped.
+const PAGE_SIZE: usize = 0x1000;
+
+/// round `size` up to the next page boundary.
+fn page_align(size: usize) -> usize {
+    (size + (PAGE_SIZE - 1)) & !(PAGE_SIZE - 1)
+}
+
+/// translate a Mach-O segment's `initprot` (`VM_PROT_READ`/`VM_PROT_WRITE`/
+/// `VM_PROT_EXECUTE`) bits into our `Permissions` bitflags.
+fn perms_from_initprot(initprot: u32) -> Permissions {
+    let mut perms = Permissions::empty();
+    if initprot & 0x1 != 0 {
+        perms |= Permissions::R;
+    }
+    if initprot & 0x2 != 0 {
+        perms |= Permissions::W;
+    }
+    if initprot & 0x4 != 0 {
+        perms |= Permissions::X;
+    }
+    perms
+}
+
+impl Loader for MachOLoader {
+    fn get_arch(&self) -> Arch {
+        self.arch
+    }
+
+    fn get_plat(&self) -> Platform {
+        Platform::MacOS
+    }
+
+    fn get_file_format(&self) -> FileFormat {
+        FileFormat::MachO
+    }
+
+    fn taste(&self, _config: &Config, buf: &[u8]) -> bool {
+        if buf.len() < 4 {
+            return false;
+        }
+
+        let magic = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+        match magic {
+            // a real fat binary's on-disk header is big-endian (`CA FE BA
+            // BE`), which `from_le_bytes` decodes as `FAT_CIGAM`, not
+            // `FAT_MAGIC` -- match both rather than silently never taking
+            // the fat branch.
+            goblin::mach::fat::FAT_MAGIC
+            | goblin::mach::fat::FAT_CIGAM
+            | goblin::mach::header::MH_MAGIC
+            | goblin::mach::header::MH_CIGAM
+            | goblin::mach::header::MH_MAGIC_64
+            | goblin::mach::header::MH_CIGAM_64 => self.select_macho(buf).is_some(),
+            _ => false,
+        }
+    }
+
+    fn load(&self, _config: &Config, buf: &[u8]) -> Result<(LoadedModule, Vec<Box<dyn Analyzer>>), Error> {
+        let macho = match Object::parse(buf) {
+            Ok(Object::Mach(Mach::Binary(macho))) => macho,
+            Ok(Object::Mach(Mach::Fat(_))) => self
+                .select_macho(buf)
+                .ok_or_else(|| failure::err_msg("no slice in fat Mach-O matches this architecture"))?,
+            _ => panic!("can't analyze unexpected format"),
+        };
+
+        let segments: Vec<&Segment> = macho
+            .segments
+            .iter()
+            .filter(|seg| seg.vmsize > 0)
+            .collect();
+
+        let base_address = segments
+            .iter()
+            .map(|seg| seg.vmaddr)
+            .min()
+            .ok_or_else(|| failure::err_msg("Mach-O file has no loadable segments"))?;
+
+        let max_address = segments
+            .iter()
+            .map(|seg| seg.vmaddr + page_align(seg.vmsize as usize) as u64)
+            .max()
+            .unwrap(); // danger: at least one segment, checked above.
+
+        let mut address_space = PageMap::with_capacity(RVA::from((max_address - base_address) as i64));
+        let mut provenance = ProvenanceMap::with_capacity(RVA::from((max_address - base_address) as i64));
+
+        let mut sections = vec![];
+        for seg in segments.iter() {
+            let rva = RVA::from((seg.vmaddr - base_address) as i64);
+            let filesize = seg.filesize as usize;
+            let memsize = seg.vmsize as usize;
+            let fileoff = seg.fileoff as usize;
+            let mapped_size = page_align(memsize);
+
+            // map the whole page-rounded region in one call: mapping
+            // `memsize` bytes directly would drop the trailing partial page
+            // via `chunks_exact`, so pad out to a full page of zeros first
+            // and overlay the real file bytes on top.
+            let mut mapped = vec![0u8; mapped_size];
+            if filesize > 0 {
+                mapped[..filesize].copy_from_slice(&buf[fileoff..fileoff + filesize]);
+                // the rest of the segment, if any, is zero-fill (e.g.
+                // `__BSS`) with no file backing: leave it `Uninitialized`.
+                provenance.mark_range(rva, rva + filesize as i64, Provenance::Data)?;
+            }
+            address_space.map(rva, &mapped)?;
+
+            let name = seg.name().unwrap_or("__UNKNOWN").to_string();
+            debug!(
+                "Mach-O: mapping segment {} at {} (size: {:#x}, perms: {:?})",
+                name,
+                rva,
+                memsize,
+                perms_from_initprot(seg.initprot)
+            );
+
+            sections.push(Section {
+                addr: rva,
+                size: memsize as u32,
+                perms: perms_from_initprot(seg.initprot),
+                name,
+            });
+        }
+
+        let module = LoadedModule {
+            base_address: VA::from(base_address),
+            sections,
+            address_space,
+            provenance,
+        };
+
+        let analyzers: Vec<Box<dyn Analyzer>> = vec![
+            Box::new(EntryPointAnalyzer::new()),
+            Box::new(EmulationAnalyzer::default()),
+            Box::new(StringAnalyzer::new()),
+        ];
+
+        Ok((module, analyzers))
+    }
+}
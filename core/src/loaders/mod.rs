@@ -0,0 +1,8 @@
+pub mod pe;
+pub mod sc;
+
+pub mod elf;
+pub mod macho;
+
+pub mod archive;
+pub mod coff;
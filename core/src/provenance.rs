@@ -0,0 +1,102 @@
+//! a parallel metadata layer over a `LoadedModule`'s bytes, mirroring the
+//! init-mask/provenance tracking used by interpreters to distinguish "real"
+//! memory from padding: rather than re-deriving whether a byte is backed by
+//! file data, holds a fixed-up pointer, or sits inside a decoded
+//! instruction by re-reading the raw bytes each time, loaders, the
+//! relocation engine, and the disassembler record that structure here as
+//! they discover it, so analyzers can look it up in O(1).
+
+use super::{arch::RVA, pagemap::PageMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provenance {
+    /// never written by a loader: `.bss`-style zero-fill with no file
+    /// backing. reads as zero, but that zero isn't meaningful data.
+    Uninitialized,
+    /// backed by file bytes (or otherwise explicitly written), but not yet
+    /// known to be a pointer or an instruction.
+    Data,
+    /// a slot the relocation engine fixed up: holds a relocated pointer.
+    Pointer,
+    /// falls within the body of a decoded instruction.
+    InstructionBody,
+}
+
+impl Default for Provenance {
+    fn default() -> Provenance {
+        Provenance::Uninitialized
+    }
+}
+
+/// page-aligned, lazily-populated per-byte provenance over a module's
+/// address space, kept separate from (but aligned page-for-page with) the
+/// bytes themselves.
+pub struct ProvenanceMap {
+    tags: PageMap<Provenance>,
+}
+
+impl ProvenanceMap {
+    pub fn with_capacity(capacity: RVA) -> ProvenanceMap {
+        ProvenanceMap {
+            tags: PageMap::with_capacity(capacity),
+        }
+    }
+
+    /// fetch the provenance of the given byte.
+    /// untouched (never marked) bytes are `Provenance::Uninitialized`.
+    ///
+    /// ```
+    /// use lancelot::arch::RVA;
+    /// use lancelot::provenance::{ProvenanceMap, Provenance};
+    ///
+    /// let provenance = ProvenanceMap::with_capacity(0x1000.into());
+    /// assert_eq!(provenance.get(0x0.into()), Provenance::Uninitialized);
+    /// ```
+    pub fn get(&self, rva: RVA) -> Provenance {
+        self.tags.get(rva).unwrap_or_default()
+    }
+
+    /// mark every byte in `[start, end)` with the given provenance.
+    ///
+    /// later marks win over earlier ones: analysis refines a byte's
+    /// classification as it learns more (e.g. `Data` -> `InstructionBody`
+    /// once the disassembler reaches it), so there's no conflict tracking
+    /// here the way there is for `shadow::ShadowAddressSpace`.
+    ///
+    /// ```
+    /// use lancelot::arch::RVA;
+    /// use lancelot::provenance::{ProvenanceMap, Provenance};
+    ///
+    /// let mut provenance = ProvenanceMap::with_capacity(0x1000.into());
+    /// provenance.mark_range(0x0.into(), 0x4.into(), Provenance::Data).unwrap();
+    /// assert_eq!(provenance.get(0x0.into()), Provenance::Data);
+    /// assert_eq!(provenance.get(0x3.into()), Provenance::Data);
+    /// assert_eq!(provenance.get(0x4.into()), Provenance::Uninitialized);
+    /// ```
+    pub fn mark_range(&mut self, start: RVA, end: RVA, provenance: Provenance) -> Result<(), super::pagemap::Error> {
+        let mut rva = start;
+        while rva < end {
+            self.tags.set(rva, provenance)?;
+            rva = rva + 1i64;
+        }
+        Ok(())
+    }
+
+    /// is the given byte anything other than untouched `.bss`-style
+    /// zero-fill? O(1): doesn't re-read or re-derive anything.
+    pub fn is_initialized(&self, rva: RVA) -> bool {
+        self.get(rva) != Provenance::Uninitialized
+    }
+
+    /// does the given byte fall within a relocation-fixed-up pointer slot?
+    /// O(1): doesn't re-read the pointer or re-probe the target.
+    pub fn is_pointer_slot(&self, rva: RVA) -> bool {
+        self.get(rva) == Provenance::Pointer
+    }
+
+    /// does the given byte fall within the body of a decoded instruction?
+    /// O(1): doesn't rescan backwards for an instruction that covers it.
+    pub fn is_in_insn(&self, rva: RVA) -> bool {
+        self.get(rva) == Provenance::InstructionBody
+    }
+}
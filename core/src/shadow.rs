@@ -0,0 +1,114 @@
+//! a parallel "shadow" address space that tags every mapped byte with a
+//! coarse classification, mirroring the valid-value shadow-bit scheme used
+//! by memory checkers (e.g. Valgrind's Memcheck / MSan).
+//!
+//! analyzers populate this as they run: the disassembler marks instruction
+//! bytes `Code`, the relocation engine marks fixed-up pointer slots `Pointer`, the
+//! string analyzer marks string bytes `String`, and so on. when two passes
+//! disagree about a byte's classification (e.g. a byte is claimed as both
+//! mid-instruction `Code` and `Data`), that's recorded as a conflict rather
+//! than silently overwritten, since it's a strong signal of data-in-code,
+//! misdisassembly, or self-modifying regions.
+
+use super::{aspace::DenseAddressSpace, arch::RVA};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tag {
+    Unknown,
+    Code,
+    Data,
+    Pointer,
+    String,
+}
+
+impl Default for Tag {
+    fn default() -> Tag {
+        Tag::Unknown
+    }
+}
+
+/// a single disagreement between two analyzers about the classification of
+/// a byte.
+#[derive(Debug, Clone, Copy)]
+pub struct Conflict {
+    pub rva:      RVA,
+    pub existing: Tag,
+    pub proposed: Tag,
+}
+
+/// page-aligned, lazily-populated byte classification over an address
+/// space, kept separate from (but aligned page-for-page with) the bytes
+/// themselves.
+pub struct ShadowAddressSpace {
+    tags:      DenseAddressSpace<Tag>,
+    conflicts: Vec<Conflict>,
+}
+
+impl ShadowAddressSpace {
+    pub fn with_capacity(capacity: RVA) -> ShadowAddressSpace {
+        ShadowAddressSpace {
+            tags:      DenseAddressSpace::with_capacity(capacity),
+            conflicts: vec![],
+        }
+    }
+
+    /// fetch the classification of the given byte.
+    /// untouched (never tagged) bytes are `Tag::Unknown`.
+    ///
+    /// ```
+    /// use lancelot::arch::RVA;
+    /// use lancelot::shadow::{ShadowAddressSpace, Tag};
+    ///
+    /// let shadow = ShadowAddressSpace::with_capacity(0x1000.into());
+    /// assert_eq!(shadow.tag(0x0.into()), Tag::Unknown);
+    /// ```
+    pub fn tag(&self, rva: RVA) -> Tag {
+        self.tags.get(rva).unwrap_or(Tag::Unknown)
+    }
+
+    /// tag every byte in `[start, end)` with the given classification.
+    ///
+    /// if a byte already carries an incompatible classification (anything
+    /// other than `Unknown` or the same tag being re-applied), the
+    /// disagreement is recorded via `conflicts()` rather than silently
+    /// overwriting the existing tag; the existing tag wins, on the theory
+    /// that the first pass to claim a byte had the most context.
+    ///
+    /// ```
+    /// use lancelot::arch::RVA;
+    /// use lancelot::shadow::{ShadowAddressSpace, Tag};
+    ///
+    /// let mut shadow = ShadowAddressSpace::with_capacity(0x1000.into());
+    /// shadow.tag_range(0x0.into(), 0x4.into(), Tag::Code).unwrap();
+    /// assert_eq!(shadow.tag(0x0.into()), Tag::Code);
+    /// assert_eq!(shadow.tag(0x3.into()), Tag::Code);
+    /// assert_eq!(shadow.tag(0x4.into()), Tag::Unknown);
+    ///
+    /// // a conflicting tag over the same byte is recorded, not applied.
+    /// shadow.tag_range(0x2.into(), 0x3.into(), Tag::Data).unwrap();
+    /// assert_eq!(shadow.tag(0x2.into()), Tag::Code);
+    /// assert_eq!(shadow.conflicts().len(), 1);
+    /// ```
+    pub fn tag_range(&mut self, start: RVA, end: RVA, tag: Tag) -> Result<(), super::aspace::Error> {
+        let mut rva = start;
+        while rva < end {
+            let existing = self.tag(rva);
+            if existing == Tag::Unknown || existing == tag {
+                self.tags.set(rva, tag)?;
+            } else {
+                self.conflicts.push(Conflict {
+                    rva,
+                    existing,
+                    proposed: tag,
+                });
+            }
+            rva = rva + 1i64;
+        }
+        Ok(())
+    }
+
+    /// fetch all recorded tag conflicts, in the order they were observed.
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+}
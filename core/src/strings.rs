@@ -0,0 +1,186 @@
+//! a cross-format analyzer that recovers printable 7-bit ASCII and UTF-16LE
+//! ("wide") string literals by scanning every readable section (UTF-8 text
+//! outside the ASCII range is not recovered), rather than relying
+//! on `Workspace::read_utf8`/`read_utf16` being pointed at a known address.
+//!
+//! the heuristics are borrowed from decomp-toolkit's string detection:
+//! a run of printable bytes becomes a string candidate once it reaches a
+//! minimum length, and is only committed once it's terminated by a NUL (so
+//! that e.g. three printable bytes followed by non-printable garbage are
+//! never mistaken for a string).
+
+use failure::Error;
+use log::debug;
+
+use super::{
+    analysis::Analyzer,
+    arch::RVA,
+    loader::Permissions,
+    shadow::Tag,
+    workspace::Workspace,
+};
+
+/// a run shorter than this is discarded rather than committed as a string,
+/// even if it's NUL-terminated: short runs are too easy to stumble into by
+/// accident inside otherwise unrelated data.
+const MIN_STRING_LENGTH: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringEncoding {
+    Ascii,
+    Utf16Le,
+}
+
+/// a recovered string literal: where it starts, how many bytes its content
+/// occupies (not including the NUL terminator), and how it was encoded.
+#[derive(Debug, Clone, Copy)]
+pub struct StringLiteral {
+    pub rva:      RVA,
+    pub length:   usize,
+    pub encoding: StringEncoding,
+}
+
+fn is_printable_ascii(b: u8) -> bool {
+    // printable, 7-bit ASCII: space through tilde.
+    (0x20..=0x7E).contains(&b)
+}
+
+/// scan `buf` (the bytes of a single section) for NUL-terminated runs of
+/// printable ASCII bytes (0x20-0x7E) at least `MIN_STRING_LENGTH` long. any
+/// other byte, including the bytes of a multi-byte UTF-8 sequence, ends a run.
+fn find_ascii_strings(base: RVA, buf: &[u8]) -> Vec<StringLiteral> {
+    let mut found = vec![];
+    let mut run_start: Option<usize> = None;
+
+    for (i, &b) in buf.iter().enumerate() {
+        if is_printable_ascii(b) {
+            if run_start.is_none() {
+                run_start = Some(i);
+            }
+            continue;
+        }
+
+        if b == 0x0 {
+            if let Some(start) = run_start.take() {
+                let length = i - start;
+                if length >= MIN_STRING_LENGTH {
+                    found.push(StringLiteral {
+                        rva: base + start as i64,
+                        length,
+                        encoding: StringEncoding::Ascii,
+                    });
+                }
+            }
+            continue;
+        }
+
+        // non-printable, non-NUL: abandon whatever run was in progress.
+        run_start = None;
+    }
+
+    found
+}
+
+/// scan `buf` for NUL-terminated runs of UTF-16LE code units whose high
+/// byte is always zero (i.e. the Basic Latin / printable ASCII range),
+/// requiring the alternating-zero high byte before ever committing a run.
+fn find_utf16_strings(base: RVA, buf: &[u8]) -> Vec<StringLiteral> {
+    let mut found = vec![];
+    let mut run_start: Option<usize> = None;
+    let mut code_units = 0usize;
+
+    for (pair_index, pair) in buf.chunks_exact(2).enumerate() {
+        let offset = pair_index * 2;
+        let (lo, hi) = (pair[0], pair[1]);
+
+        if hi == 0x0 && lo == 0x0 {
+            // a double-zero code unit is the wide terminator.
+            if let Some(start) = run_start.take() {
+                if code_units >= MIN_STRING_LENGTH {
+                    found.push(StringLiteral {
+                        rva:      base + start as i64,
+                        length:   code_units * 2,
+                        encoding: StringEncoding::Utf16Le,
+                    });
+                }
+            }
+            code_units = 0;
+            continue;
+        }
+
+        if hi == 0x0 && is_printable_ascii(lo) {
+            if run_start.is_none() {
+                run_start = Some(offset);
+            }
+            code_units += 1;
+            continue;
+        }
+
+        // a non-Latin/non-printable code unit: abandon the run in progress.
+        run_start = None;
+        code_units = 0;
+    }
+
+    found
+}
+
+/// scans every readable section for string literals.
+///
+/// ```
+/// use lancelot::test;
+/// use lancelot::analysis::Analyzer;
+/// use lancelot::strings::{StringAnalyzer, StringEncoding};
+///
+/// // NOPs, then a NUL-terminated ASCII run, then more NOPs.
+/// let mut ws = test::get_shellcode32_workspace(b"\x90\x90\x90\x90AAAA\x00\x90\x90");
+/// StringAnalyzer::new().analyze(&mut ws).unwrap();
+///
+/// let found = ws.strings.iter().find(|s| s.rva == 4.into()).unwrap();
+/// assert_eq!(found.length, 4);
+/// assert_eq!(found.encoding, StringEncoding::Ascii);
+/// ```
+pub struct StringAnalyzer {}
+
+impl StringAnalyzer {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> StringAnalyzer {
+        StringAnalyzer {}
+    }
+}
+
+impl Analyzer for StringAnalyzer {
+    fn get_name(&self) -> String {
+        "string-recovery analyzer".to_string()
+    }
+
+    fn analyze(&self, ws: &mut Workspace) -> Result<(), Error> {
+        for section in ws.module.sections.iter().filter(|section| section.perms.intersects(Permissions::R)) {
+            let buf = match ws.read_bytes(section.addr, section.size as usize) {
+                Ok(buf) => buf,
+                Err(e) => {
+                    debug!("string analyzer: failed to read section {}: {}", section.name, e);
+                    continue;
+                }
+            };
+
+            let mut literals = find_ascii_strings(section.addr, &buf);
+            literals.extend(find_utf16_strings(section.addr, &buf));
+
+            debug!("string analyzer: found {} candidates in {}", literals.len(), section.name);
+            for literal in literals.iter() {
+                // best-effort: a tagging failure (e.g. a literal straddling
+                // the edge of the mapped space) doesn't invalidate the
+                // recovered literal itself.
+                let _ = ws.shadow.tag_range(literal.rva, literal.rva + literal.length as i64, Tag::String);
+            }
+            ws.strings.extend(literals);
+        }
+
+        // TODO: coalesce adjacent literals that share a single string-base
+        // symbol (e.g. a table of strings referenced only by offset into a
+        // shared blob) into one logical string, rather than leaving each
+        // NUL-terminated run as an independent literal.
+
+        Ok(())
+    }
+}
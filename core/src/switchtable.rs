@@ -0,0 +1,184 @@
+//! recovery of `jmp [reg*scale + table]`-style indirect jump-table
+//! dispatch, shared by `get_basic_blocks` (which resolves a table inline so
+//! a single CFG walk discovers every case) and
+//! `analysis::pe::jumptable::JumpTableAnalyzer` (which re-sweeps already
+//! discovered functions as a fallback, e.g. once the emulator or reloc
+//! analyzer has resolved more of the surrounding code).
+
+use super::{
+    arch::{RVA, VA},
+    loader::Permissions,
+    workspace::Workspace,
+};
+
+/// sanity cap on the number of table entries we'll read when the bounds
+/// check can't be recovered (or lies), so that a corrupt/adversarial table
+/// doesn't cause us to read gigabytes of "entries".
+pub const MAX_TABLE_ENTRIES: usize = 0x1000;
+
+/// how far back (in instructions) we're willing to walk within a basic
+/// block looking for the bounds-check/table-load pattern.
+pub const MAX_BACKWARD_WINDOW: usize = 20;
+
+/// the recovered shape of an indirect jump dispatch, e.g.:
+///
+/// ```text
+/// cmp  eax, 0x9        ; case count (N = 0xA)
+/// ja   default_case
+/// lea  rcx, [table]
+/// mov  eax, [rcx + rax*4]
+/// add  rax, rcx         ; (only for rva-relative tables)
+/// jmp  rax
+/// ```
+pub struct SwitchTable {
+    /// address of the indirect jmp itself.
+    pub jmp_address: RVA,
+    /// base address of the table of targets/deltas.
+    pub table_address: RVA,
+    /// width in bytes of each table entry (1, 2, 4, or 8).
+    pub entry_width: usize,
+    /// number of entries, recovered from the bounds check (or capped).
+    pub count: usize,
+}
+
+/// is the instruction at `addr` an indirect `jmp` through a memory operand
+/// that has a base register, e.g. `jmp [rcx + rax*8]` (as opposed to a direct
+/// `jmp label` or an indirect `jmp reg`)? a direct jump is already handled by
+/// ordinary xref recovery; an indirect jump through a bare register (no
+/// table) isn't one this pass can resolve. a memory operand with no base
+/// register, such as `jmp [eax*4 + table]`, is not matched.
+pub fn is_indirect_jmp_through_memory(ws: &Workspace, addr: RVA) -> bool {
+    let insn = match ws.read_insn(addr).ok().and_then(|insn| insn.x86) {
+        Some(insn) => insn,
+        None => return false,
+    };
+
+    insn.mnemonic == zydis::Mnemonic::JMP && insn.operands.iter().any(|op| op.mem.base != zydis::Register::NONE)
+}
+
+/// walk the instructions of a basic block backwards from `jmp_insn`,
+/// looking for the `cmp reg, N` / `ja default` bounds check and the
+/// `lea base, [table]` / `mov target, [base + idx*scale]` table load.
+///
+/// ```
+/// use lancelot::test;
+/// use lancelot::arch::RVA;
+/// use lancelot::switchtable::recover_switch_table;
+///
+/// // cmp eax, 0x9 ; lea ecx, [0x2000] ; mov eax, [ecx + eax*4] ; jmp eax
+/// let ws = test::get_shellcode32_workspace(b"\x83\xF8\x09\x8D\x0D\x00\x20\x00\x00\x8B\x04\x81\xFF\xE0");
+/// let insns = vec![RVA(0x0), RVA(0x3), RVA(0x9), RVA(0xC)];
+///
+/// let table = recover_switch_table(&ws, &insns, 3).unwrap();
+/// assert_eq!(table.table_address, RVA(0x2000));
+/// assert_eq!(table.entry_width, 4);
+/// // `cmp eax, 0x9` means the highest valid index is 9, so there are 10 cases.
+/// assert_eq!(table.count, 10);
+/// ```
+pub fn recover_switch_table(ws: &Workspace, insns: &[RVA], jmp_index: usize) -> Option<SwitchTable> {
+    let jmp_address = insns[jmp_index];
+
+    let mut table_address: Option<RVA> = None;
+    let mut entry_width: usize = 0;
+    let mut count: Option<usize> = None;
+
+    let window_start = jmp_index.saturating_sub(MAX_BACKWARD_WINDOW);
+
+    for &addr in insns[window_start..jmp_index].iter().rev() {
+        let insn = match ws.read_insn(addr).ok().and_then(|insn| insn.x86) {
+            Some(insn) => insn,
+            None => continue,
+        };
+
+        match insn.mnemonic {
+            zydis::Mnemonic::LEA => {
+                // `lea base, [table_rva]`: establishes the table address.
+                if let Some(op) = insn.operands.iter().find(|op| op.mem.disp.has_displacement) {
+                    table_address = Some(RVA::from(op.mem.disp.displacement));
+                }
+            }
+            zydis::Mnemonic::MOV | zydis::Mnemonic::MOVZX | zydis::Mnemonic::MOVSXD => {
+                // `mov target, [base + idx*scale]`: establishes the entry width.
+                if let Some(op) = insn.operands.iter().find(|op| op.mem.scale > 0) {
+                    entry_width = (op.size / 8) as usize;
+                }
+            }
+            zydis::Mnemonic::CMP => {
+                // `cmp idx, N`: establishes the case count (N is the highest valid
+                // index, so the count is N + 1).
+                if let Some(op) = insn.operands.iter().find(|op| op.ty == zydis::OperandType::IMMEDIATE) {
+                    if op.imm.value != 0 || op.size > 0 {
+                        count = Some((op.imm.value as usize).saturating_add(1));
+                    }
+                }
+                // once we've found the bounds check, the pattern is complete.
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let table_address = table_address?;
+    let entry_width = if entry_width == 0 { 4 } else { entry_width };
+    let count = count.unwrap_or(MAX_TABLE_ENTRIES).min(MAX_TABLE_ENTRIES);
+
+    Some(SwitchTable {
+        jmp_address,
+        table_address,
+        entry_width,
+        count,
+    })
+}
+
+/// read the raw table bytes and interpret each entry as a code target,
+/// stopping at the first entry that lands outside a mapped, executable page,
+/// or that overlaps the table itself (a sign the bounds/width were mis-recovered).
+pub fn resolve_targets(ws: &Workspace, table: &SwitchTable) -> Vec<RVA> {
+    let table_end = table.table_address + (table.count * table.entry_width) as i64;
+
+    let mut targets = vec![];
+    for i in 0..table.count {
+        let entry_rva = table.table_address + (i * table.entry_width) as i64;
+
+        let raw = match table.entry_width {
+            1 => ws.read_u8(entry_rva).map(|v| v as i64).ok(),
+            2 => ws.read_u16(entry_rva).map(|v| v as i64).ok(),
+            4 => ws.read_i32(entry_rva).map(|v| v as i64).ok(),
+            8 => ws.read_i64(entry_rva).ok(),
+            _ => None,
+        };
+
+        let raw = match raw {
+            Some(raw) => raw,
+            None => break,
+        };
+
+        // on x64, some tables store an RVA-sized delta relative to the table
+        // itself rather than an absolute RVA; prefer whichever interpretation
+        // actually lands in a mapped, executable page. absolute entries are
+        // VAs (image-base-relative), so they must go through `ws.rva` rather
+        // than being reinterpreted as an RVA directly.
+        let absolute = ws.rva(VA::from(raw as u64));
+        let relative = table.table_address + raw;
+
+        let candidate = if absolute.map_or(false, |absolute| ws.probe(absolute, 1, Permissions::X)) {
+            absolute.unwrap()
+        } else if ws.probe(relative, 1, Permissions::X) {
+            relative
+        } else {
+            // first entry that doesn't resolve to code: stop enumerating,
+            // since the recovered count was likely an overestimate (or this
+            // isn't a switch table after all).
+            break;
+        };
+
+        // the table must not overlap the code it's being read from.
+        if candidate >= table.table_address && candidate < table_end {
+            break;
+        }
+
+        targets.push(candidate);
+    }
+
+    targets
+}
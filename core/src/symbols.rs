@@ -0,0 +1,218 @@
+//! import/export of a plain-text "symbol map": one entry per line, giving
+//! an address, a name, a size, and a visibility. modeled on decomp-toolkit's
+//! map support, this lets names and entry points recovered by a previous
+//! run (or imported from an external tool, e.g. a linker map or a
+//! hand-annotated database) seed analysis instead of being rediscovered --
+//! or never discovered at all -- from scratch every time.
+//!
+//! map format, one entry per line:
+//!
+//! ```text
+//! <va> <visibility> <size> <name>
+//! 0x401000 global 0x10 main
+//! 0x401010 local 0x4 .L0
+//! ```
+//!
+//! blank lines and `#`-prefixed comments are ignored.
+
+use std::fmt;
+
+use failure::{Error, Fail};
+
+use super::{arch::VA, workspace::Workspace};
+
+#[derive(Debug, Fail)]
+pub enum SymbolMapError {
+    #[fail(display = "invalid symbol map line: {}", _0)]
+    InvalidLine(String),
+}
+
+/// how broadly a symbol is expected to be referenced: a `Global` symbol
+/// becomes a function analysis root when imported, while a `Local` one
+/// (e.g. a recovered basic-block label or string) only attaches a name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    Global,
+    Local,
+}
+
+impl fmt::Display for Visibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Visibility::Global => write!(f, "global"),
+            Visibility::Local => write!(f, "local"),
+        }
+    }
+}
+
+/// a single entry from a symbol map.
+#[derive(Debug, Clone)]
+pub struct SymbolMapEntry {
+    pub va:         VA,
+    pub name:       String,
+    pub size:       u64,
+    pub visibility: Visibility,
+}
+
+fn parse_hex(field: &str) -> Result<u64, Error> {
+    let digits = field.trim_start_matches("0x");
+    u64::from_str_radix(digits, 16).map_err(|_| SymbolMapError::InvalidLine(field.to_string()).into())
+}
+
+/// parse a symbol map of the form `<va> <visibility> <size> <name>`, one
+/// entry per line. blank lines and `#`-prefixed comments are ignored.
+///
+/// Example:
+///
+/// ```
+/// use lancelot::symbols::{parse_symbol_map, Visibility};
+///
+/// let entries = parse_symbol_map("# comment\n0x401000 global 0x10 main\n").unwrap();
+/// assert_eq!(entries.len(), 1);
+/// assert_eq!(entries[0].name, "main");
+/// assert_eq!(entries[0].visibility, Visibility::Global);
+/// ```
+pub fn parse_symbol_map(s: &str) -> Result<Vec<SymbolMapEntry>, Error> {
+    let mut entries = vec![];
+
+    for line in s.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != 4 {
+            return Err(SymbolMapError::InvalidLine(line.to_string()).into());
+        }
+
+        let va = VA::from(parse_hex(fields[0])?);
+        let visibility = match fields[1] {
+            "global" | "g" => Visibility::Global,
+            "local" | "l" => Visibility::Local,
+            other => return Err(SymbolMapError::InvalidLine(format!("unknown visibility: {}", other)).into()),
+        };
+        let size = parse_hex(fields[2])?;
+        let name = fields[3].to_string();
+
+        entries.push(SymbolMapEntry { va, name, size, visibility });
+    }
+
+    Ok(entries)
+}
+
+/// render entries in the same format accepted by `parse_symbol_map`,
+/// ordered by address.
+pub fn render_symbol_map(entries: &[SymbolMapEntry]) -> String {
+    let mut entries: Vec<&SymbolMapEntry> = entries.iter().collect();
+    entries.sort_by_key(|e| e.va);
+
+    let mut out = String::new();
+    for e in entries.iter() {
+        let va: u64 = e.va.into();
+        out.push_str(&format!("{:#x} {} {:#x} {}\n", va, e.visibility, e.size, e.name));
+    }
+    out
+}
+
+/// apply a parsed symbol map to `ws`, attaching each entry's name to its
+/// RVA and, for `Global` entries, seeding a function analysis root.
+///
+/// intended to run before any analyzer, so imported names/entry points
+/// seed analysis rather than race it. entries whose VA falls outside this
+/// module are skipped rather than treated as an error, since a map
+/// exported from a different (but related) binary commonly carries a few
+/// addresses that don't apply here.
+///
+/// Example:
+///
+/// ```
+/// use lancelot::test;
+/// use lancelot::arch::RVA;
+/// use lancelot::symbols::{self, SymbolMapEntry, Visibility};
+///
+/// let mut ws = test::get_shellcode32_workspace(b"\xEB\xFE");
+/// let va = ws.va(RVA(0x0)).unwrap();
+/// let entries = vec![SymbolMapEntry {
+///     va,
+///     name: "start".to_string(),
+///     size: 0x2,
+///     visibility: Visibility::Global,
+/// }];
+///
+/// symbols::apply_symbol_map(&mut ws, &entries).unwrap();
+/// assert_eq!(ws.get_symbol_name(RVA(0x0)), Some("start"));
+/// ```
+pub fn apply_symbol_map(ws: &mut Workspace, entries: &[SymbolMapEntry]) -> Result<(), Error> {
+    for entry in entries.iter() {
+        let rva = match ws.rva(entry.va) {
+            Some(rva) => rva,
+            None => continue,
+        };
+
+        ws.make_symbol(rva, &entry.name)?;
+        ws.symbols.insert(rva, entry.name.clone());
+
+        if entry.visibility == Visibility::Global {
+            ws.make_function(rva)?;
+        }
+    }
+
+    ws.analyze()
+}
+
+/// export every function discovered so far (named, and sized by its basic
+/// blocks), the basic-block boundaries within each, and the recovered
+/// string literals, as a symbol map that `parse_symbol_map` can read back.
+pub fn export_symbol_map(ws: &Workspace) -> Vec<SymbolMapEntry> {
+    let mut entries = vec![];
+
+    for &rva in ws.get_functions() {
+        let va = match ws.va(rva) {
+            Some(va) => va,
+            None => continue,
+        };
+
+        let bbs = match ws.get_basic_blocks(rva) {
+            Ok(bbs) => bbs,
+            Err(_) => continue,
+        };
+        let size: u64 = bbs.iter().map(|bb| bb.length).sum();
+
+        let name = ws.get_symbol_name(rva).map(|s| s.to_string()).unwrap_or_else(|| format!("sub_{}", rva));
+        entries.push(SymbolMapEntry {
+            va,
+            name,
+            size,
+            visibility: Visibility::Global,
+        });
+
+        for bb in bbs.iter() {
+            if bb.addr == rva {
+                // the function's entry block is already captured above.
+                continue;
+            }
+            if let Some(bb_va) = ws.va(bb.addr) {
+                entries.push(SymbolMapEntry {
+                    va:         bb_va,
+                    name:       format!("lbl_{}", bb.addr),
+                    size:       bb.length,
+                    visibility: Visibility::Local,
+                });
+            }
+        }
+    }
+
+    for literal in ws.get_strings().iter() {
+        if let Some(va) = ws.va(literal.rva) {
+            entries.push(SymbolMapEntry {
+                va,
+                name: format!("str_{}", literal.rva),
+                size: literal.length as u64,
+                visibility: Visibility::Local,
+            });
+        }
+    }
+
+    entries
+}
@@ -0,0 +1,193 @@
+//! transparent unwrapping of a compressed/packed input into the image that
+//! the loaders actually understand, so `WorkspaceBuilder::load` can accept
+//! a gzip/zlib blob (or a trivial length-prefixed LZ wrapper) directly
+//! instead of requiring callers to decompress it out of band first.
+//!
+//! modeled on decomp-toolkit's transparent Yaz0 handling: containers are
+//! unwrapped recursively (a gzip blob wrapping a zlib blob wrapping the
+//! real image, say), and the chain of layers peeled off is kept around so
+//! an address in the final, analyzed image can still be traced back to
+//! roughly where it came from in the original file (see `origin_offset`).
+
+use failure::{Error, Fail};
+
+#[derive(Debug, Fail)]
+pub enum UnwrapError {
+    #[fail(display = "truncated container header")]
+    Truncated,
+    #[fail(display = "corrupt {} stream", _0)]
+    Corrupt(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerKind {
+    Gzip,
+    Zlib,
+    /// a minimal length-prefixed LZ77-style wrapper: a 4-byte magic
+    /// (`"LZ01"`), a 4-byte little-endian decompressed size, then a stream
+    /// of tokens -- a `0x00` tag followed by one literal byte, or a
+    /// non-zero tag followed by a 16-bit `(distance:12, length:4)`
+    /// back-reference (length biased by 3, so it covers 3..18 bytes).
+    Lz,
+}
+
+/// one layer of unwrapping that was peeled off: the payload came from
+/// `outer_len` compressed bytes at the front of the previous layer's buffer
+/// (layer 0's "previous buffer" is the original file).
+#[derive(Debug, Clone, Copy)]
+pub struct UnwrapLayer {
+    pub kind:      ContainerKind,
+    pub outer_len: usize,
+}
+
+/// refuse to recurse through more than this many nested containers, so a
+/// pathological (or adversarial) chain of wrappers can't loop or exhaust
+/// memory.
+const MAX_UNWRAP_DEPTH: usize = 8;
+
+fn detect(buf: &[u8]) -> Option<ContainerKind> {
+    if buf.len() >= 2 && buf[0] == 0x1F && buf[1] == 0x8B {
+        Some(ContainerKind::Gzip)
+    } else if buf.len() >= 2 && buf[0] == 0x78 && matches!(buf[1], 0x01 | 0x5E | 0x9C | 0xDA) {
+        Some(ContainerKind::Zlib)
+    } else if buf.len() >= 8 && &buf[0..4] == b"LZ01" {
+        Some(ContainerKind::Lz)
+    } else {
+        None
+    }
+}
+
+fn inflate_gzip(buf: &[u8]) -> Result<Vec<u8>, Error> {
+    use std::io::Read;
+    let mut out = vec![];
+    flate2::read::GzDecoder::new(buf).read_to_end(&mut out)?;
+    Ok(out)
+}
+
+fn inflate_zlib(buf: &[u8]) -> Result<Vec<u8>, Error> {
+    use std::io::Read;
+    let mut out = vec![];
+    flate2::read::ZlibDecoder::new(buf).read_to_end(&mut out)?;
+    Ok(out)
+}
+
+/// decode the toy `ContainerKind::Lz` wrapper described above.
+fn inflate_lz(buf: &[u8]) -> Result<Vec<u8>, Error> {
+    if buf.len() < 8 {
+        return Err(UnwrapError::Truncated.into());
+    }
+
+    let expected_len = u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]) as usize;
+    let mut out = Vec::with_capacity(expected_len);
+    let mut pos = 8;
+
+    while pos < buf.len() && out.len() < expected_len {
+        let tag = buf[pos];
+        pos += 1;
+
+        if tag == 0x00 {
+            let byte = *buf.get(pos).ok_or(UnwrapError::Corrupt("lz"))?;
+            out.push(byte);
+            pos += 1;
+        } else {
+            if pos + 1 >= buf.len() {
+                return Err(UnwrapError::Corrupt("lz").into());
+            }
+            let token = u16::from_le_bytes([buf[pos], buf[pos + 1]]);
+            pos += 2;
+
+            let distance = (token >> 4) as usize;
+            let length = ((token & 0xF) + 3) as usize;
+            if distance == 0 || distance > out.len() {
+                // a back-reference can't point further back than what's
+                // already been produced; treat this as the end of a
+                // corrupt or overlapping stream rather than panicking.
+                return Err(UnwrapError::Corrupt("lz").into());
+            }
+
+            let start = out.len() - distance;
+            for i in 0..length {
+                let byte = out[start + i];
+                out.push(byte);
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+fn inflate(kind: ContainerKind, buf: &[u8]) -> Result<Vec<u8>, Error> {
+    match kind {
+        ContainerKind::Gzip => inflate_gzip(buf),
+        ContainerKind::Zlib => inflate_zlib(buf),
+        ContainerKind::Lz => inflate_lz(buf),
+    }
+}
+
+/// recursively unwrap `buf` through any recognized container layers,
+/// stopping at the first buffer that isn't itself a recognized container
+/// (or decompression fails -- a false-positive header match on an
+/// already-final image, say).
+///
+/// returns the innermost payload along with the chain of layers that were
+/// peeled off, outermost first; an input with no recognized container
+/// simply returns `(buf.to_vec(), vec![])`.
+pub fn unwrap(buf: &[u8]) -> (Vec<u8>, Vec<UnwrapLayer>) {
+    let mut layers = vec![];
+    let mut current = buf.to_vec();
+
+    while layers.len() < MAX_UNWRAP_DEPTH {
+        let kind = match detect(&current) {
+            Some(kind) => kind,
+            None => break,
+        };
+
+        let inflated = match inflate(kind, &current) {
+            Ok(inflated) => inflated,
+            Err(_) => break,
+        };
+
+        layers.push(UnwrapLayer {
+            kind,
+            outer_len: current.len(),
+        });
+
+        current = inflated;
+    }
+
+    (current, layers)
+}
+
+/// trace an offset in the final (innermost) buffer back through the unwrap
+/// chain to roughly where it came from in the original file.
+///
+/// decompression doesn't preserve a byte-for-byte offset mapping, so every
+/// offset within a layer's decompressed payload maps back to the start of
+/// that layer's compressed bytes -- good enough to say "this address came
+/// from the gzip blob that starts the file", not to recover an exact
+/// pre-compression byte position. since each peeled layer's container spans
+/// the entirety of its predecessor's buffer (`unwrap` always consumes
+/// `outer_len` == that whole buffer), that start is offset `0` regardless of
+/// how many layers were peeled; an empty layer stack (nothing was
+/// unwrapped) passes `offset` through unchanged.
+///
+/// ```
+/// use lancelot::unwrap::{origin_offset, unwrap};
+///
+/// let (_, layers) = unwrap(b"not a container");
+/// assert!(layers.is_empty());
+/// assert_eq!(origin_offset(&layers, 5), 5);
+///
+/// // a valid (empty-payload) gzip stream, so `unwrap` actually peels a layer.
+/// let gzipped = b"\x1F\x8B\x08\x00\x00\x00\x00\x00\x02\xFF\x03\x00\x00\x00\x00\x00\x00\x00\x00\x00";
+/// let (_, layers) = unwrap(gzipped);
+/// assert_eq!(layers.len(), 1);
+/// assert_eq!(origin_offset(&layers, 5), 0);
+/// ```
+pub fn origin_offset(layers: &[UnwrapLayer], offset: usize) -> usize {
+    if layers.is_empty() {
+        offset
+    } else {
+        0
+    }
+}
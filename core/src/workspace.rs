@@ -3,14 +3,21 @@ use std::collections::{HashMap, VecDeque};
 use byteorder::{ByteOrder, LittleEndian};
 use failure::{Error, Fail};
 use log::{info, warn};
-use zydis::{self, Decoder};
 
 use super::{
     analysis::Analysis,
     arch::{Arch, RVA, VA},
     basicblock::BasicBlock,
     config::Config,
+    disasm::{DecodedInsn, Disassembler, ZydisDisassembler},
+    emulator,
     loader::{self, LoadedModule, Loader, Permissions},
+    provenance::Provenance,
+    shadow::{ShadowAddressSpace, Tag},
+    strings::StringLiteral,
+    switchtable,
+    symbols::{self, SymbolMapEntry},
+    unwrap::{self, UnwrapLayer},
     util,
     xref::XrefType,
 };
@@ -38,6 +45,18 @@ pub struct WorkspaceBuilder {
 
     /// when true, the analysis failures should fail the loading of the module.
     strict_mode: bool,
+
+    /// entries imported via `with_symbol_map`, applied after loading but
+    /// before any analyzer runs.
+    symbol_map: Option<Vec<SymbolMapEntry>>,
+
+    /// overrides the loader-arch-selected default `ZydisDisassembler`.
+    decoder: Option<Box<dyn Disassembler>>,
+
+    /// when true (the default), `load()` transparently decompresses a
+    /// gzip/zlib/LZ-wrapped input before handing it to the loaders. see
+    /// `with_unwrapping`.
+    should_unwrap: bool,
 }
 
 impl WorkspaceBuilder {
@@ -70,6 +89,42 @@ impl WorkspaceBuilder {
         WorkspaceBuilder { config, ..self }
     }
 
+    /// Override the default, loader-arch-selected `ZydisDisassembler` with
+    /// an alternate `Disassembler` backend, e.g. to analyze an ISA other
+    /// than x86.
+    pub fn with_decoder(self: WorkspaceBuilder, decoder: Box<dyn Disassembler>) -> WorkspaceBuilder {
+        WorkspaceBuilder {
+            decoder: Some(decoder),
+            ..self
+        }
+    }
+
+    /// Toggle transparent container/packer unwrapping (on by default): when
+    /// enabled, `load()` recursively peels off any recognized gzip/zlib/LZ
+    /// wrapper around `buf` before handing it to the loaders, so a packed
+    /// blob can be dropped in directly. disable this if `buf` happens to
+    /// start with bytes that collide with a container signature but isn't
+    /// actually wrapped.
+    pub fn with_unwrapping(self: WorkspaceBuilder, enabled: bool) -> WorkspaceBuilder {
+        WorkspaceBuilder {
+            should_unwrap: enabled,
+            ..self
+        }
+    }
+
+    /// Load a symbol map (see `symbols::parse_symbol_map`) from `path` and
+    /// apply it once the module is loaded, before any analyzer runs, so
+    /// imported names/entry points seed analysis rather than race it.
+    pub fn with_symbol_map(self: WorkspaceBuilder, path: &str) -> Result<WorkspaceBuilder, Error> {
+        info!("loading symbol map: {}", path);
+        let text = String::from_utf8(util::read_file(path)?)?;
+        let entries = symbols::parse_symbol_map(&text)?;
+        Ok(WorkspaceBuilder {
+            symbol_map: Some(entries),
+            ..self
+        })
+    }
+
     /// Construct a workspace with the given builder configuration.
     ///
     /// This invokes the loaders, analyzers, and another other logic,
@@ -110,15 +165,25 @@ impl WorkspaceBuilder {
     ///   .map_err(|e| panic!(e));
     /// ```
     pub fn load(self: WorkspaceBuilder) -> Result<Workspace, Error> {
+        let (buf, layers) = if self.should_unwrap {
+            let (buf, layers) = unwrap::unwrap(&self.buf);
+            if !layers.is_empty() {
+                info!("unwrapped {} container layer(s)", layers.len());
+            }
+            (buf, layers)
+        } else {
+            (self.buf, vec![])
+        };
+
         // if the user provided a loader, use that.
         // otherwise, use the default detected loader.
         let (ldr, module, analyzers) = match self.loader {
             // TODO: let users specify analyzers via builder
             Some(ldr) => {
-                let (module, analyzers) = ldr.load(&self.config, &self.buf)?;
+                let (module, analyzers) = ldr.load(&self.config, &buf)?;
                 (ldr, module, analyzers)
             }
-            None => loader::load(&self.config, &self.buf)?,
+            None => loader::load(&self.config, &buf)?,
         };
 
         info!("loaded {} sections:", module.sections.len());
@@ -128,14 +193,16 @@ impl WorkspaceBuilder {
 
         let analysis = Analysis::new(&module);
 
-        let decoder = match ldr.get_arch() {
-            Arch::X32 => Decoder::new(zydis::MachineMode::LEGACY_32, zydis::AddressWidth::_32).unwrap(),
-            Arch::X64 => Decoder::new(zydis::MachineMode::LONG_64, zydis::AddressWidth::_64).unwrap(),
+        let decoder: Box<dyn Disassembler> = match self.decoder {
+            Some(decoder) => decoder,
+            None => Box::new(ZydisDisassembler::new(ldr.get_arch())),
         };
 
+        let shadow = ShadowAddressSpace::with_capacity(module.max_address());
+
         let mut ws = Workspace {
             filename: self.filename,
-            buf: self.buf,
+            buf,
 
             loader: ldr,
             module,
@@ -143,8 +210,16 @@ impl WorkspaceBuilder {
             decoder,
 
             analysis,
+            strings: vec![],
+            symbols: HashMap::new(),
+            layers,
+            shadow,
         };
 
+        if let Some(entries) = self.symbol_map.as_ref() {
+            symbols::apply_symbol_map(&mut ws, entries)?;
+        }
+
         if self.should_analyze {
             for analyzer in analyzers.iter() {
                 info!("analyzing with {}", analyzer.get_name());
@@ -157,6 +232,13 @@ impl WorkspaceBuilder {
             }
         }
 
+        for conflict in ws.shadow.conflicts() {
+            warn!(
+                "shadow: conflicting classification at {}: {:?} (existing) vs {:?} (proposed)",
+                conflict.rva, conflict.existing, conflict.proposed
+            );
+        }
+
         Ok(ws)
     }
 }
@@ -170,10 +252,28 @@ pub struct Workspace {
     pub loader: Box<dyn Loader>,
     pub module: LoadedModule,
 
-    pub decoder: Decoder,
+    pub decoder: Box<dyn Disassembler>,
 
     // pub only so that we can split the impl
     pub analysis: Analysis,
+
+    /// string literals recovered by `strings::StringAnalyzer`, in the order
+    /// they were discovered. see `get_strings`/`get_string_at`.
+    pub strings: Vec<StringLiteral>,
+
+    /// names attached via `symbols::apply_symbol_map`. see `get_symbol_name`.
+    pub symbols: HashMap<RVA, String>,
+
+    /// container/packer layers peeled off `buf` by `WorkspaceBuilder::load`,
+    /// outermost first. see `origin_offset`.
+    pub layers: Vec<UnwrapLayer>,
+
+    /// coarse per-byte classification (`Code`/`Data`/`Pointer`/`String`),
+    /// populated alongside `module.provenance` as analyzers run. disagreeing
+    /// analyzers don't overwrite each other; see `shadow::ShadowAddressSpace`
+    /// and `shadow.conflicts()`, which `WorkspaceBuilder::load` logs a
+    /// warning for once loading completes.
+    pub shadow: ShadowAddressSpace,
 }
 
 impl Workspace {
@@ -188,6 +288,9 @@ impl Workspace {
             loader:         None,
             should_analyze: true,
             strict_mode:    false,
+            symbol_map:     None,
+            decoder:        None,
+            should_unwrap:  true,
         }
     }
 
@@ -199,6 +302,9 @@ impl Workspace {
             loader:         None,
             should_analyze: true,
             strict_mode:    false,
+            symbol_map:     None,
+            decoder:        None,
+            should_unwrap:  true,
         })
     }
 
@@ -240,6 +346,54 @@ impl Workspace {
             .and_then(Ok)
     }
 
+    /// Overwrite the bytes at the given RVA, e.g. to apply a relocation fixup.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use lancelot::test;
+    /// use lancelot::arch::RVA;
+    ///
+    /// let mut ws = test::get_shellcode32_workspace(b"\xEB\xFE");
+    /// ws.write_bytes(RVA(0x0), b"\x90\x90").unwrap();
+    /// assert_eq!(ws.read_u16(RVA(0x0)).unwrap(), 0x9090);
+    /// ```
+    pub fn write_bytes(&mut self, rva: RVA, buf: &[u8]) -> Result<(), Error> {
+        for (i, &b) in buf.iter().enumerate() {
+            self.module
+                .address_space
+                .set(rva + i as i64, b)
+                .map_err(|_| WorkspaceError::InvalidAddress)?;
+        }
+        Ok(())
+    }
+
+    /// Overwrite the byte at the given RVA, e.g. to apply a relocation fixup.
+    pub fn write_u8(&mut self, rva: RVA, value: u8) -> Result<(), Error> {
+        self.write_bytes(rva, &[value])
+    }
+
+    /// Overwrite the word at the given RVA, e.g. to apply a relocation fixup.
+    pub fn write_u16(&mut self, rva: RVA, value: u16) -> Result<(), Error> {
+        let mut buf = [0u8; 2];
+        LittleEndian::write_u16(&mut buf, value);
+        self.write_bytes(rva, &buf)
+    }
+
+    /// Overwrite the dword at the given RVA, e.g. to apply a relocation fixup.
+    pub fn write_u32(&mut self, rva: RVA, value: u32) -> Result<(), Error> {
+        let mut buf = [0u8; 4];
+        LittleEndian::write_u32(&mut buf, value);
+        self.write_bytes(rva, &buf)
+    }
+
+    /// Overwrite the qword at the given RVA, e.g. to apply a relocation fixup.
+    pub fn write_u64(&mut self, rva: RVA, value: u64) -> Result<(), Error> {
+        let mut buf = [0u8; 8];
+        LittleEndian::write_u64(&mut buf, value);
+        self.write_bytes(rva, &buf)
+    }
+
     /// Is the given range mapped?
     ///
     /// Example:
@@ -416,10 +570,10 @@ impl Workspace {
     /// let ws = test::get_shellcode32_workspace(b"\xEB\xFE");
     /// assert_eq!(ws.read_insn(RVA(0x0)).is_ok(), true);
     /// assert_eq!(ws.read_insn(RVA(0x0)).unwrap().length, 2);
-    /// assert_eq!(ws.read_insn(RVA(0x0)).unwrap().mnemonic, zydis::Mnemonic::JMP);
+    /// assert_eq!(ws.read_insn(RVA(0x0)).unwrap().x86.unwrap().mnemonic, zydis::Mnemonic::JMP);
     /// ```
     #[allow(clippy::collapsible_if)]
-    pub fn read_insn(&self, rva: RVA) -> Result<zydis::DecodedInstruction, Error> {
+    pub fn read_insn(&self, rva: RVA) -> Result<DecodedInsn, Error> {
         let mut buf = [0u8; 0x10];
 
         // we expect instructions to be at most 0x10 bytes long.
@@ -452,6 +606,23 @@ impl Workspace {
         Err(WorkspaceError::InvalidAddress.into())
     }
 
+    /// Decode the instruction at the given RVA and tag its body
+    /// `Provenance::InstructionBody` in `module.provenance`, so later
+    /// lookups (e.g. the reloc analyzer's `is_in_insn`) are O(1) instead of
+    /// re-decoding backwards from scratch.
+    ///
+    /// Errors: same as `read_insn`.
+    pub fn mark_insn_provenance(&mut self, rva: RVA) -> Result<(), Error> {
+        let length = self.read_insn(rva)?.length as i64;
+        self.module
+            .provenance
+            .mark_range(rva, rva + length, Provenance::InstructionBody)
+            .map_err(|_| WorkspaceError::InvalidAddress.into())?;
+        self.shadow
+            .tag_range(rva, rva + length, Tag::Code)
+            .map_err(|_| WorkspaceError::InvalidAddress.into())
+    }
+
     /// Read a utf-8 encoded string at the given RVA.
     /// Only strings less than 0x1000 bytes are currently recognized.
     ///
@@ -495,6 +666,77 @@ impl Workspace {
         Ok(std::str::from_utf8(sbuf)?.to_string())
     }
 
+    /// Read a UTF-16LE ("wide") encoded string at the given RVA.
+    /// Only strings less than 0x1000 bytes (0x800 UTF-16 code units) are
+    /// currently recognized.
+    ///
+    /// Errors:
+    ///
+    ///   - InvalidAddress - if the address is not mapped.
+    ///   - std::char::DecodeUtf16Error - if the data is not valid UTF-16.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use lancelot::test;
+    /// use lancelot::arch::RVA;
+    ///
+    /// let ws = test::get_shellcode32_workspace(b"\x00\x00A\x00A\x00\x00\x00");
+    /// assert_eq!(ws.read_utf16(RVA(0x2)).unwrap(), "AA");
+    /// ```
+    pub fn read_utf16(&self, rva: RVA) -> Result<String, Error> {
+        let mut buf = [0u8; 0x1000];
+
+        if self.module.address_space.slice_into(rva, &mut buf).is_err() {
+            self.module
+                .sections
+                .iter()
+                .find(|section| section.contains(rva))
+                .ok_or_else(|| WorkspaceError::InvalidAddress.into())
+                .and_then(|section| {
+                    let size: usize = (section.end() - rva).into();
+                    let size = std::cmp::min(size, 0x1000);
+                    self.module.address_space.slice_into(rva, &mut buf[..size])
+                })?;
+        }
+
+        let code_units: Vec<u16> = buf
+            .chunks_exact(2)
+            .map(|pair| LittleEndian::read_u16(pair))
+            .take_while(|&cu| cu != 0x0)
+            .collect();
+
+        let s: Result<String, _> = std::char::decode_utf16(code_units).collect();
+        Ok(s?)
+    }
+
+    /// Fetch every string literal recovered by `strings::StringAnalyzer`, in
+    /// the order they were discovered.
+    pub fn get_strings(&self) -> &[StringLiteral] {
+        &self.strings
+    }
+
+    /// Fetch the string literal (if any) that starts at the given RVA.
+    pub fn get_string_at(&self, rva: RVA) -> Option<&StringLiteral> {
+        self.strings.iter().find(|literal| literal.rva == rva)
+    }
+
+    /// Fetch the name (if any) attached to the given RVA via
+    /// `symbols::apply_symbol_map`/`WorkspaceBuilder::with_symbol_map`.
+    pub fn get_symbol_name(&self, rva: RVA) -> Option<&str> {
+        self.symbols.get(&rva).map(String::as_str)
+    }
+
+    /// map `rva` (an address in `self.buf`, the possibly-unwrapped image
+    /// that was actually loaded) back through any container/packer layers
+    /// that `WorkspaceBuilder::load` peeled off, to an offset in the
+    /// original file bytes. returns the offset unchanged when nothing was
+    /// unwrapped.
+    pub fn origin_offset(&self, rva: RVA) -> usize {
+        let offset: usize = rva.into();
+        unwrap::origin_offset(&self.layers, offset)
+    }
+
     pub fn rva(&self, va: VA) -> Option<RVA> {
         if va < self.module.base_address {
             return None;
@@ -549,12 +791,13 @@ impl Workspace {
                 let mut has_fallthrough = false;
                 // does the instruction flow elsewhere (jnz, jmp, cmov)?
                 let mut has_flow_from = false;
-                for xref in self.get_xrefs_from(current_insn)?.iter() {
-                    match xref.typ {
+                let decoded = self.read_insn(current_insn)?;
+                for (typ, dst) in self.decoder.classify(self, current_insn, &decoded) {
+                    match typ {
                         XrefType::Fallthrough => has_fallthrough = true,
                         XrefType::UnconditionalJump | XrefType::ConditionalJump | XrefType::ConditionalMove => {
                             has_flow_from = true;
-                            current_bb.successors.push(xref.dst);
+                            current_bb.successors.push(dst);
                         }
                         XrefType::Call => {}
                     }
@@ -581,6 +824,36 @@ impl Workspace {
 
                     // flow successors were already added above,
                     // when enumerating the xrefs-from.
+
+                    if current_bb.successors.is_empty() && switchtable::is_indirect_jmp_through_memory(self, current_insn) {
+                        // `jmp [reg*scale + table]`: static xref recovery can't
+                        // resolve this, since the target isn't encoded directly
+                        // in the instruction. recover the table right here so
+                        // its cases are enqueued for discovery like any other
+                        // successor, rather than waiting for a later analyzer
+                        // pass to notice the dead end.
+                        //
+                        // note: these are reported as plain RVA successors,
+                        // same as every other edge in this CFG -- `XrefType`
+                        // doesn't currently distinguish a recovered indirect
+                        // jump from a direct one.
+                        if let Some(table) = switchtable::recover_switch_table(self, &current_bb.insns, current_bb.insns.len() - 1) {
+                            let targets = switchtable::resolve_targets(self, &table);
+                            current_bb.successors.extend(targets);
+                        }
+                    }
+
+                    if current_bb.successors.is_empty() {
+                        // not a table dispatch: maybe a bare register/computed
+                        // jump (`jmp rax`) whose target a short, read-only
+                        // trace of this block can resolve without needing a
+                        // mutable workspace (that's `EmulationAnalyzer`'s job,
+                        // run separately over whole functions).
+                        if let Some(result) = emulator::resolve_block_target(self, &current_bb.insns) {
+                            current_bb.successors.push(result.target);
+                        }
+                    }
+
                     break 'insns;
                 } else if has_flow_from {
                     // end of basic block
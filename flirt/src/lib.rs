@@ -141,6 +141,38 @@ impl FlirtSignature {
 
         return None;
     }
+
+    /// fetch the reference-name records (the `^xxxx name` entries) embedded
+    /// in this signature: offsets, relative to the start of the matched
+    /// function, at which a called subroutine should be given the paired
+    /// name.
+    ///
+    /// these let a single FLIRT match propagate names transitively to the
+    /// library routines it calls, not just to the matched function itself.
+    pub fn get_references(&self) -> Vec<(u16, &str)> {
+        self.names
+            .iter()
+            .filter_map(|name| match name {
+                Symbol::Reference(name) => Some((name.offset, name.name.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// a rough measure of how specific this signature is: the number of
+    /// non-wildcard bytes within the matched region.
+    ///
+    /// used to pick a winner when two distinct signatures would otherwise
+    /// apply the same name to two different functions: the longer/more
+    /// exact match is assumed to be the correct one.
+    pub fn specificity(&self) -> u16 {
+        self.byte_sig
+            .0
+            .iter()
+            .take(self.size_of_function as usize)
+            .filter(|b| matches!(b, SigElement::Byte(_)))
+            .count() as u16
+    }
 }
 
 pub struct FlirtSignatureMatcher<'a> {